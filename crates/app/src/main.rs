@@ -3,18 +3,25 @@
     windows_subsystem = "windows"
 )]
 
-use std::{collections::HashSet, time::Duration};
+use std::{collections::HashSet, num::NonZeroUsize, sync::Arc};
+use arc_swap::ArcSwapOption;
 use freya::prelude::{reexports::winit::window::WindowLevel, *};
-use futures_util::{SinkExt, StreamExt};
+use futures_util::StreamExt;
 use kv_log_macro::{info, warn};
 
 use lcu::{
-    cmd::get_cmd_output,
-    lcu_api::{make_champion_avatar_url, make_sub_msg, make_ws_client},
-    reqwest_websocket::Message,
-    serde_json::{from_str, Value},
+    asset_cache::AssetCache,
+    builds::BuildSection,
+    cmd::{ClientSelector, CommandLineOutput},
+    discovery_error::DiscoveryError,
+    events::{EventType, LcuEventClient, Reconnect},
+    game_mode::GameMode,
+    gameflow::{GameflowPhase, GameflowTracker},
+    lcu_api::LcuClient,
+    recording::EventRecorder,
     source::SourceItem,
-    web::fetch_sources
+    watcher::{ClientEvent, ClientWatcher},
+    web::{fetch_sources, list_builds_for_mode}
 };
 
 fn main() {
@@ -124,91 +131,163 @@ fn another_window() -> Element {
     let platform = use_platform();
     let onpress = move |_| platform.close_window();
 
-    let mut lcu_auth_url = use_signal(|| String::new());
+    // icons are downloaded once per game version, see `lcu::asset_cache`
+    let assets = use_hook(|| {
+        let root = dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("champr")
+            .join("assets");
+        AssetCache::open(root, NonZeroUsize::new(256).unwrap())
+            .map(Arc::new)
+            .map_err(|e| warn!("error opening asset cache: {}", e))
+            .ok()
+    });
+
+    // the client picked by the watcher, the event client follows it too
+    let credentials = use_hook(|| Arc::new(ArcSwapOption::<CommandLineOutput>::empty()));
+    let event_credentials = credentials.clone();
+
+    let mut lcu_client = use_signal::<Option<LcuClient>>(|| None);
     use_effect(move || {
+        let assets = assets.clone();
+        let credentials = credentials.clone();
+        // `CHAMPR_CLIENT=pid:<pid>` or `region:<region>` picks among several clients
+        let selector = std::env::var("CHAMPR_CLIENT")
+            .ok()
+            .and_then(|value| ClientSelector::parse(&value))
+            .unwrap_or_default();
         spawn(async move {
-            loop {
-                let url = lcu_auth_url.read().clone();
-                if let Ok(ret) = get_cmd_output() {
-                    if !url.eq(&ret.auth_url) {
-                        lcu_auth_url.write().clear();
-                        *lcu_auth_url.write() = ret.auth_url.clone();
+            let (_watcher, mut events) = ClientWatcher::default().selector(selector).spawn();
+            while let Some(event) = events.recv().await {
+                match event {
+                    ClientEvent::ClientStarted(client) | ClientEvent::CredentialsChanged(client) => {
+                        info!("tracking league client: {}", client.pid);
+                        credentials.store(Some(Arc::new(client.clone())));
+                        match LcuClient::from_cmd_output(&client) {
+                            Ok(mut client) => {
+                                if let Some(assets) = assets.clone() {
+                                    client = client.with_asset_cache(assets.clone());
+                                    let prefetching = client.clone();
+                                    spawn(async move {
+                                        if let Err(e) = assets.prefetch_icons(&prefetching).await {
+                                            warn!("error prefetching icons: {}", e);
+                                        }
+                                    });
+                                }
+                                *lcu_client.write() = Some(client);
+                            }
+                            Err(e) => warn!("error creating lcu client: {:?}", e),
+                        }
+                    }
+                    ClientEvent::ClientStopped(_) => {
+                        warn!("league client stopped");
+                        credentials.store(None);
+                        *lcu_client.write() = None;
                     }
-                } else {
-                    warn!("error getting auth url output");
                 }
-                tokio::time::sleep(Duration::from_millis(2500)).await;
             }
         });
     });
 
     let mut champion_id = use_signal::<u64>(|| 0);
+    let mut game_mode = use_signal(GameMode::default);
     use_effect(move || {
-        let endpoint = lcu_auth_url.read().clone();
-        if endpoint.is_empty() {
-            return;
-        }
-        let cur_cid = champion_id.read().clone();
+        let credentials = event_credentials.clone();
         spawn(async move {
-            let ws = make_ws_client(&endpoint).await;
-            if let Ok(ws) = ws {
-                let (mut tx, mut rx) = ws.split();
-                if let Err(e) = tx.send(make_sub_msg()).await {
-                    info!("error sending message: {}", e);
-                }
-                loop {
-                    while let Some(msg) = rx.next().await {
-                        match msg {
-                            Ok(msg) => {
-                                if let Message::Text(msg) = msg {
-                                    if msg.is_empty() {
-                                        continue;
-                                    }
-                                    let parsed: Value = match from_str(&msg) {
-                                        Ok(parsed) => parsed,
-                                        Err(e) => {
-                                            warn!("error parsing ws message: {}", e);
-                                            continue;
-                                        }
-                                    };
-                                    let data = parsed.get(2)
-                                        .and_then(|v| v.as_object());
-                                    let uri = data
-                                        .and_then(|v| v.get("uri"))
-                                        .and_then(|v| v.as_str());
-                                    if uri.is_none() {
-                                        continue;
-                                    }
-                                    if let Some(uri) = uri {
-                                        if uri != "/lol-champ-select/v1/summoners/0" {
-                                            continue;
-                                        }
-                                    }
-                                    // info!("[ws] data {:?}", data);
-                                    let cid = data
-                                        .and_then(|v| v.get("data"))
-                                        .and_then(|v| v.get("championId"))
-                                        .and_then(|v| v.as_u64());
-                                    if let Some(cid) = cid {
-                                        if cur_cid != cid {
-                                            info!("update champion id: {:?}", cid);
-                                            *champion_id.write() = cid;
-                                        }
-                                    } else {
-                                        *champion_id.write() = 0;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                warn!("error receiving message: {}", e);
-                                return;
+            let reconnect = Reconnect::default().credentials_with(move || {
+                credentials
+                    .load_full()
+                    .map(|client| (*client).clone())
+                    .ok_or(DiscoveryError::ProcessNotFound)
+            });
+            let events = LcuEventClient::spawn(reconnect);
+            // captures the session for reproducing bugs, see `lcu::recording`
+            let _recorder = match std::env::var("CHAMPR_RECORD_EVENTS") {
+                Ok(path) => EventRecorder::start(&events, path)
+                    .await
+                    .map_err(|e| warn!("error starting event recorder: {}", e))
+                    .ok(),
+                Err(_) => None,
+            };
+            let (gameflow, mut transitions) = GameflowTracker::spawn(&events);
+            let mut summoner = events.subscribe("/lol-champ-select/v1/summoners/0");
+            spawn(async move {
+                let _gameflow = gameflow;
+                while let Some(transition) = transitions.next().await {
+                    // also covers the client going away
+                    if transition.left(&GameflowPhase::ChampSelect) {
+                        *champion_id.write() = 0;
+                    }
+                    if transition.entered(&GameflowPhase::ChampSelect) {
+                        let Some(client) = lcu_client.peek().clone() else {
+                            continue;
+                        };
+                        match client.get_game_mode().await {
+                            Ok(mode) => {
+                                info!("game mode: {:?}", mode);
+                                *game_mode.write() = mode;
                             }
+                            Err(e) => warn!("error reading game mode: {}", e),
                         }
                     }
                 }
+            });
+
+            while let Some(event) = summoner.next().await {
+                let cid = match event.event_type {
+                    EventType::Delete => None,
+                    _ => event.data.get("championId").and_then(|v| v.as_u64()),
+                };
+                let cid = cid.unwrap_or_default();
+                if *champion_id.peek() != cid {
+                    info!("update champion id: {:?}", cid);
+                    *champion_id.write() = cid;
+                }
+            }
+        });
+    });
+
+    let mut sources = use_signal::<Vec<SourceItem>>(|| vec![]);
+    use_effect(move || {
+        spawn(async move {
+            if let Ok(list) = fetch_sources().await {
+                *sources.write() = list;
             }
-           if let Err(e) = ws {
-                warn!("error creating websocket client: {}", e);
+        });
+    });
+
+    let mut builds = use_signal::<Vec<(String, Vec<BuildSection>)>>(|| vec![]);
+    use_effect(move || {
+        let cid = champion_id.read().clone();
+        let mode = game_mode.read().clone();
+        let sources = sources.read().clone();
+        if cid == 0 {
+            builds.write().clear();
+            return;
+        }
+        spawn(async move {
+            let list = list_builds_for_mode(&sources, &mode, cid as i64).await;
+            info!("got builds of {} from {} sources for {:?}", cid, list.len(), mode);
+            *builds.write() = list;
+        });
+    });
+
+    // loaded through the authenticated client, the token stays out of urls
+    let mut champion_icon = use_signal(|| None);
+    use_effect(move || {
+        let cid = champion_id.read().clone();
+        let client = lcu_client.read().clone();
+        spawn(async move {
+            let icon = match (cid > 0, client) {
+                (true, Some(client)) => client
+                    .get_champion_icon_by_id(cid as i64)
+                    .await
+                    .map_err(|e| warn!("error loading champion icon: {:?}", e))
+                    .ok(),
+                _ => None,
+            };
+            if *champion_id.peek() == cid {
+                *champion_icon.write() = icon;
             }
         });
     });
@@ -224,13 +303,11 @@ fn another_window() -> Element {
 
     rsx!(
         {
-            let cid = champion_id.read().clone();
-            if cid > 0 {
-                let lcu_auth_url = lcu_auth_url.read().clone();
-                let champion_avatar = make_champion_avatar_url(&lcu_auth_url, cid);
+            let icon = champion_icon.read().clone();
+            if let Some(icon) = icon {
                 rsx!(
-                    NetworkImage {
-                        url: champion_avatar,
+                    image {
+                        image_data: dynamic_bytes(icon),
                         width: "64",
                         height: "64",
                     }
@@ -251,6 +328,14 @@ fn another_window() -> Element {
             label {
                 "Champion ID: {champion_id}"
             }
+            {
+                let names = builds.read().iter().map(|(source, _)| source.clone()).collect::<Vec<_>>().join(", ");
+                rsx!(
+                    label {
+                        "Builds: {names}"
+                    }
+                )
+            }
             Button {
                 onpress,
                 label { "Close" }
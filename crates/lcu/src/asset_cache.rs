@@ -0,0 +1,372 @@
+//! Disk and memory cache of LCU assets such as perk and champion icons, so
+//! they are downloaded once per game version and still render after the
+//! client closed.
+
+use std::{
+    fmt,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use bytes::Bytes;
+use futures::StreamExt;
+use image::DynamicImage;
+use kv_log_macro::{info, warn};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{lcu_api::LcuClient, lcu_error::LcuError};
+
+const VERSION_FILE: &str = "version";
+const ASSETS_DIR: &str = "assets";
+const PREFETCH_CONCURRENCY: usize = 8;
+const ASSETS_ROOT: &str = "/lol-game-data/assets/v1/";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetError {
+    Io(String),
+    /// Not cached and no client to download it from.
+    NotCached(String),
+    Fetch(String),
+    Decode(String),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(msg) => write!(f, "asset cache io error: {msg}"),
+            AssetError::NotCached(path) => write!(f, "asset not cached: {path}"),
+            AssetError::Fetch(path) => write!(f, "error downloading asset: {path}"),
+            AssetError::Decode(msg) => write!(f, "error decoding image: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+impl From<std::io::Error> for AssetError {
+    fn from(error: std::io::Error) -> AssetError {
+        AssetError::Io(error.to_string())
+    }
+}
+
+/// `/lol-game-data/assets/v1/champion-summary.json`
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChampionSummary {
+    pub id: i64,
+    pub name: String,
+    pub alias: String,
+    pub square_portrait_path: String,
+}
+
+/// Accepts both the `iconPath` of a perk, which is already absolute, and
+/// paths relative to the assets root.
+pub fn rune_image_path(icon_path: &str) -> String {
+    if icon_path.starts_with(ASSETS_ROOT) {
+        return icon_path.to_string();
+    }
+    format!("{ASSETS_ROOT}{}", icon_path.trim_start_matches('/'))
+}
+
+pub fn champion_icon_path(id: i64) -> String {
+    format!("{ASSETS_ROOT}champion-icons/{id}.png")
+}
+
+/// Assets by LCU path and game version.
+///
+/// Files are named after the hash of both, so a patch never serves stale
+/// icons and old files are simply not read anymore. The last seen version
+/// is kept on disk, which lets the cache answer without a client.
+#[derive(Debug)]
+pub struct AssetCache {
+    dir: PathBuf,
+    version: Mutex<String>,
+    memory: Mutex<LruCache<String, Bytes>>,
+}
+
+impl AssetCache {
+    /// Opens or creates the cache in `dir`, keeping up to `capacity` assets
+    /// in memory.
+    pub fn open(dir: impl Into<PathBuf>, capacity: NonZeroUsize) -> Result<Self, AssetError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(dir.join(ASSETS_DIR))?;
+        let version = std::fs::read_to_string(dir.join(VERSION_FILE)).unwrap_or_default();
+
+        Ok(Self {
+            dir,
+            version: Mutex::new(version.trim().to_string()),
+            memory: Mutex::new(LruCache::new(capacity)),
+        })
+    }
+
+    pub fn version(&self) -> String {
+        self.version.lock().unwrap().clone()
+    }
+
+    pub async fn set_version(&self, version: &str) -> Result<(), AssetError> {
+        if *self.version.lock().unwrap() == version {
+            return Ok(());
+        }
+        info!("[asset_cache] game version {}", version);
+        tokio::fs::write(self.dir.join(VERSION_FILE), version).await?;
+        *self.version.lock().unwrap() = version.to_string();
+        Ok(())
+    }
+
+    /// Reads the game version of `client`, call it whenever a client
+    /// connects.
+    pub async fn sync_version(&self, client: &LcuClient) -> Result<String, LcuError> {
+        let version = client.get_game_version().await?;
+        self.set_version(&version)
+            .await
+            .map_err(|e| LcuError::APIError(e.to_string()))?;
+        Ok(version)
+    }
+
+    fn key(&self, path: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.version().as_bytes());
+        hasher.update(b"\n");
+        hasher.update(path.as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn file(&self, key: &str) -> PathBuf {
+        self.dir.join(ASSETS_DIR).join(&key[..2]).join(key)
+    }
+
+    /// The asset from memory or disk, without downloading it.
+    pub async fn get_cached(&self, path: &str) -> Option<Bytes> {
+        let key = self.key(path);
+        if let Some(bytes) = self.memory.lock().unwrap().get(&key) {
+            return Some(bytes.clone());
+        }
+
+        let bytes = Bytes::from(tokio::fs::read(self.file(&key)).await.ok()?);
+        self.memory.lock().unwrap().put(key, bytes.clone());
+        Some(bytes)
+    }
+
+    /// The asset at `path`, downloaded from `client` on a miss.
+    pub async fn get(&self, client: Option<&LcuClient>, path: &str) -> Result<Bytes, AssetError> {
+        if let Some(bytes) = self.get_cached(path).await {
+            return Ok(bytes);
+        }
+        let Some(client) = client else {
+            return Err(AssetError::NotCached(path.to_string()));
+        };
+
+        let bytes = client
+            .fetch_asset(path)
+            .await
+            .map_err(|_| AssetError::Fetch(path.to_string()))?;
+        let key = self.key(path);
+        if let Err(e) = write_file(&self.file(&key), &bytes).await {
+            warn!("[asset_cache] error writing {}: {}", path, e);
+        }
+        self.memory.lock().unwrap().put(key, bytes.clone());
+        Ok(bytes)
+    }
+
+    pub async fn get_image(
+        &self,
+        client: Option<&LcuClient>,
+        path: &str,
+    ) -> Result<DynamicImage, AssetError> {
+        decode_image(&self.get(client, path).await?)
+    }
+
+    /// Downloads the missing assets of `paths`, returns how many are cached
+    /// afterwards.
+    pub async fn prefetch(&self, client: &LcuClient, paths: Vec<String>) -> usize {
+        futures::stream::iter(paths)
+            .map(|path| async move { self.get(Some(client), &path).await.is_ok() })
+            .buffer_unordered(PREFETCH_CONCURRENCY)
+            .filter(|ok| futures::future::ready(*ok))
+            .count()
+            .await
+    }
+
+    /// Caches every perk and champion icon, meant to run when a client
+    /// connects.
+    pub async fn prefetch_icons(&self, client: &LcuClient) -> Result<usize, LcuError> {
+        self.sync_version(client).await?;
+        let (perks, champions) = futures::try_join!(
+            client.list_all_perks(),
+            client.get::<Vec<ChampionSummary>>("/lol-game-data/assets/v1/champion-summary.json"),
+        )?;
+
+        let paths: Vec<String> = perks
+            .iter()
+            .map(|p| p.icon_path.clone())
+            .chain(
+                champions
+                    .iter()
+                    .filter(|c| c.id > 0)
+                    .map(|c| champion_icon_path(c.id)),
+            )
+            .filter(|p| !p.is_empty())
+            .collect();
+        let total = paths.len();
+        let cached = self.prefetch(client, paths).await;
+        info!("[asset_cache] prefetched {}/{} icons", cached, total);
+        Ok(cached)
+    }
+}
+
+/// Writes through a temporary file, so readers never see half an asset.
+async fn write_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let tmp = path.with_extension(format!("{}.tmp", nanoid::nanoid!(6)));
+    tokio::fs::write(&tmp, bytes).await?;
+    tokio::fs::rename(&tmp, path).await
+}
+
+pub fn decode_image(bytes: &[u8]) -> Result<DynamicImage, AssetError> {
+    image::load_from_memory(bytes).map_err(|e| AssetError::Decode(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockLcu;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("lcu-assets-{}", nanoid::nanoid!(8)))
+    }
+
+    fn asset_requests(mock: &MockLcu) -> usize {
+        mock.requests()
+            .iter()
+            .filter(|r| {
+                r.path
+                    .starts_with("/lol-game-data/assets/v1/champion-icons")
+            })
+            .count()
+    }
+
+    #[tokio::test]
+    async fn cache_by_version() {
+        let mock = MockLcu::start().await;
+        let icon = champion_icon_path(103);
+        mock.respond("GET", &icon, 200, json!({ "icon": 103 }));
+        let dir = temp_dir();
+        let capacity = NonZeroUsize::new(8).unwrap();
+
+        let cache = AssetCache::open(&dir, capacity).unwrap();
+        cache.set_version("14.20.1").await.unwrap();
+        let client = mock.client();
+        let bytes = cache.get(Some(&client), &icon).await.unwrap();
+        assert_eq!(bytes, Bytes::from(r#"{"icon":103}"#));
+        assert_eq!(cache.get(Some(&client), &icon).await.unwrap(), bytes);
+        assert_eq!(asset_requests(&mock), 1);
+
+        // from disk, without a client
+        let reopened = AssetCache::open(&dir, capacity).unwrap();
+        assert_eq!(reopened.version(), "14.20.1");
+        assert_eq!(reopened.get(None, &icon).await.unwrap(), bytes);
+
+        reopened.set_version("14.21.1").await.unwrap();
+        assert_eq!(
+            reopened.get(None, &icon).await,
+            Err(AssetError::NotCached(icon.clone()))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn client_reads_through_cache() {
+        let mock = MockLcu::start().await;
+        let electrocute = "/lol-game-data/assets/v1/perk-images/Electrocute.png";
+        mock.respond("GET", electrocute, 200, json!([8112]));
+        mock.respond("GET", &champion_icon_path(103), 200, json!([103]));
+        let dir = temp_dir();
+        let cache = AssetCache::open(&dir, NonZeroUsize::new(8).unwrap()).unwrap();
+        let client = mock.client().with_asset_cache(Arc::new(cache));
+
+        for _ in 0..2 {
+            // the perk `iconPath` is absolute already
+            assert_eq!(
+                client.get_rune_image(electrocute).await.unwrap(),
+                Bytes::from("[8112]")
+            );
+            assert_eq!(
+                client.get_champion_icon_by_id(103).await.unwrap(),
+                Bytes::from("[103]")
+            );
+        }
+        let paths: Vec<String> = mock.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            vec![electrocute.to_string(), champion_icon_path(103)]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn prefetch_icons() {
+        let mock = MockLcu::start().await;
+        mock.respond("GET", "/lol-patch/v1/game-version", 200, json!("14.20.1"));
+        mock.respond(
+            "GET",
+            "/lol-perks/v1/perks",
+            200,
+            json!([serde_json::to_value(crate::lcu_api::Perk {
+                id: 8112,
+                icon_path: "/lol-game-data/assets/v1/perk-images/Electrocute.png".to_string(),
+                ..Default::default()
+            })
+            .unwrap()]),
+        );
+        mock.respond(
+            "GET",
+            "/lol-game-data/assets/v1/champion-summary.json",
+            200,
+            json!([{ "id": -1, "name": "None" }, { "id": 1 }, { "id": 103 }]),
+        );
+        for path in [
+            "/lol-game-data/assets/v1/perk-images/Electrocute.png".to_string(),
+            champion_icon_path(103),
+        ] {
+            mock.respond("GET", &path, 200, json!([1]));
+        }
+
+        let dir = temp_dir();
+        let cache = AssetCache::open(&dir, NonZeroUsize::new(1).unwrap()).unwrap();
+        // the icon of champion 1 is missing
+        assert_eq!(cache.prefetch_icons(&mock.client()).await.unwrap(), 2);
+        assert_eq!(cache.version(), "14.20.1");
+        assert!(cache.get_cached(&champion_icon_path(103)).await.is_some());
+        assert!(cache.get_cached(&champion_icon_path(1)).await.is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn decode_png() {
+        let mut png = std::io::Cursor::new(vec![]);
+        DynamicImage::new_rgba8(2, 3)
+            .write_to(&mut png, image::ImageOutputFormat::Png)
+            .unwrap();
+
+        let image = decode_image(png.get_ref()).unwrap();
+        assert_eq!((image.width(), image.height()), (2, 3));
+        assert!(matches!(
+            decode_image(b"not an image"),
+            Err(AssetError::Decode(_))
+        ));
+        let icon = "/lol-game-data/assets/v1/perk-images/Styles/Domination.png";
+        assert_eq!(rune_image_path("/perk-images/Styles/Domination.png"), icon);
+        assert_eq!(rune_image_path(icon), icon);
+    }
+}
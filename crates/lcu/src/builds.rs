@@ -1,15 +1,21 @@
 use futures::StreamExt;
-use kv_log_macro::info;
+use kv_log_macro::{info, warn};
 use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashSet,
     fs,
     io::Write,
     path::Path,
     sync::{Arc, Mutex},
 };
 
-use crate::web::{self, ChampionsMap, FetchError};
+use crate::{
+    install_dir::InstallDir,
+    lcu_api::LcuClient,
+    lcu_error::LcuError,
+    web::{self, ChampionsMap, FetchError},
+};
 
 pub type LogItem = (String, String);
 
@@ -91,18 +97,13 @@ pub fn empty_rune_type() -> String {
 
 pub fn apply_builds_from_data(
     sections: Vec<BuildSection>,
-    dir: &String,
+    install_dir: &InstallDir,
     source: &str,
     champion_alias: &String,
-    is_tencent: bool,
 ) {
     use kv_log_macro as log;
 
-    let folder = if is_tencent {
-        format!("{dir}/Game/Config/Champions")
-    } else {
-        format!("{dir}/Config/Champions")
-    };
+    let folder = install_dir.champions_folder();
     let parent_dir = format!("{folder}/{champion_alias}/Recommended");
     if let Err(err) = fs::create_dir_all(&parent_dir) {
         log::error!("failed to create dir: {:?}", err);
@@ -124,11 +125,42 @@ pub fn apply_builds_from_data(
     }
 }
 
+/// Where applied builds end up.
+#[derive(Debug, Clone)]
+pub enum BuildSink {
+    /// JSON files under `Config/Champions/<alias>/Recommended`.
+    Files(InstallDir),
+    /// Account item sets, uploaded through the LCU.
+    ItemSets(LcuClient),
+}
+
+impl BuildSink {
+    pub async fn apply(
+        &self,
+        sections: Vec<BuildSection>,
+        source: &str,
+        champion_alias: &String,
+    ) -> Result<(), LcuError> {
+        match self {
+            BuildSink::Files(install_dir) => {
+                apply_builds_from_data(sections, install_dir, source, champion_alias);
+                Ok(())
+            }
+            BuildSink::ItemSets(client) => {
+                let builds: Vec<ItemBuild> =
+                    sections.into_iter().flat_map(|s| s.item_builds).collect();
+                client
+                    .upload_item_sets(source, champion_alias, &builds)
+                    .await
+            }
+        }
+    }
+}
+
 pub async fn apply_builds_from_source(
-    dir: &String,
+    install_dir: &InstallDir,
     source: &String,
     champion: &String,
-    is_tencent: bool,
 ) -> Result<(), FetchError> {
     let sections = match web::list_builds_by_alias(source, champion).await {
         Ok(s) => s,
@@ -136,7 +168,7 @@ pub async fn apply_builds_from_source(
             return Err(FetchError::Failed);
         }
     };
-    apply_builds_from_data(sections, dir, source, champion, is_tencent);
+    apply_builds_from_data(sections, install_dir, source, champion);
 
     Ok(())
 }
@@ -174,20 +206,51 @@ pub async fn fetch_and_apply(
     Ok(())
 }
 
+#[derive(Default, Debug, Clone)]
+pub struct ImportOptions {
+    /// Only import champions the summoner logged into this client owns,
+    /// rents or has free to play.
+    pub playable_for: Option<LcuClient>,
+}
+
+/// Keeps the champions of `champions_map` whose numeric `key` is in `ids`.
+pub fn filter_champions(champions_map: ChampionsMap, ids: &HashSet<i64>) -> ChampionsMap {
+    champions_map
+        .into_iter()
+        .filter(|(_, info)| info.key.parse().is_ok_and(|id| ids.contains(&id)))
+        .collect()
+}
+
 pub async fn batch_apply(
     selected_sources: Vec<String>,
     champions_map: ChampionsMap,
-    dir: String,
-    is_tencent: bool,
+    install_dir: InstallDir,
     logs: Arc<Mutex<Vec<LogItem>>>,
+    options: ImportOptions,
 ) -> Result<(), ()> {
+    let champions_map = match &options.playable_for {
+        Some(client) => match client.list_playable_champion_ids().await {
+            Ok(ids) => {
+                let total = champions_map.len();
+                let playable = filter_champions(champions_map, &ids);
+                info!(
+                    "[apply_builds] importing {} of {} champions",
+                    playable.len(),
+                    total
+                );
+                playable
+            }
+            Err(e) => {
+                warn!("[apply_builds] importing all champions, {}", e);
+                champions_map
+            }
+        },
+        None => champions_map,
+    };
+
     let mut tasks = vec![];
 
-    let folder = if is_tencent {
-        format!("{dir}/Game/Config/Champions")
-    } else {
-        format!("{dir}/Config/Champions")
-    };
+    let folder = install_dir.champions_folder();
     if Path::new(&folder).exists() {
         let _ = fs::remove_dir_all(&folder);
     } else {
@@ -235,4 +298,28 @@ mod tests {
         let target = String::from(".test");
         fetch_and_apply(&target, &String::from("op.gg"), &String::from("Rengar")).await
     }
+
+    #[test]
+    fn filter_owned_champions() {
+        let champion = |alias: &str, key: &str| {
+            (
+                alias.to_string(),
+                web::ChampInfo {
+                    id: alias.to_string(),
+                    key: key.to_string(),
+                    ..Default::default()
+                },
+            )
+        };
+        let champions: ChampionsMap = [
+            champion("Annie", "1"),
+            champion("Rengar", "107"),
+            champion("Broken", "n/a"),
+        ]
+        .into_iter()
+        .collect();
+
+        let playable = filter_champions(champions, &HashSet::from([107, 266]));
+        assert_eq!(playable.keys().collect::<Vec<_>>(), vec!["Rengar"]);
+    }
 }
@@ -0,0 +1,243 @@
+use serde::{Deserialize, Serialize};
+
+/// `/lol-champ-select/v1/session`, fields the client may omit default to
+/// empty values.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ChampSelectSession {
+    pub actions: Vec<Vec<Action>>,
+    pub allow_duplicate_picks: bool,
+    pub allow_rerolling: bool,
+    pub bans: Bans,
+    pub bench_champions: Vec<BenchChampion>,
+    pub bench_enabled: bool,
+    pub game_id: i64,
+    pub has_simultaneous_bans: bool,
+    pub has_simultaneous_picks: bool,
+    pub is_custom_game: bool,
+    pub is_spectating: bool,
+    pub local_player_cell_id: i64,
+    pub my_team: Vec<TeamMember>,
+    pub rerolls_remaining: i64,
+    pub their_team: Vec<TeamMember>,
+    pub timer: Timer,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TeamMember {
+    pub assigned_position: String,
+    pub cell_id: i64,
+    pub champion_id: i64,
+    pub champion_pick_intent: i64,
+    pub selected_skin_id: i64,
+    pub spell1_id: u64,
+    pub spell2_id: u64,
+    pub summoner_id: i64,
+    pub team: i64,
+    pub ward_skin_id: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Action {
+    pub actor_cell_id: i64,
+    pub champion_id: i64,
+    pub completed: bool,
+    pub id: i64,
+    pub is_ally_action: bool,
+    pub is_in_progress: bool,
+    pub pick_turn: i64,
+    #[serde(rename = "type")]
+    pub type_field: String,
+}
+
+impl Action {
+    pub fn is_ban(&self) -> bool {
+        self.type_field == "ban"
+    }
+
+    pub fn is_pick(&self) -> bool {
+        self.type_field == "pick"
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Bans {
+    pub my_team_bans: Vec<i64>,
+    pub num_bans: i64,
+    pub their_team_bans: Vec<i64>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BenchChampion {
+    pub champion_id: i64,
+    pub is_priority: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Timer {
+    pub adjusted_time_left_in_phase: i64,
+    pub internal_now_in_epoch_ms: i64,
+    pub is_infinite: bool,
+    pub phase: String,
+    pub total_time_in_phase: i64,
+}
+
+impl ChampSelectSession {
+    pub fn me(&self) -> Option<&TeamMember> {
+        self.my_team
+            .iter()
+            .find(|m| m.cell_id == self.local_player_cell_id)
+    }
+
+    pub fn my_actions(&self) -> impl Iterator<Item = &Action> {
+        self.actions
+            .iter()
+            .flatten()
+            .filter(move |a| a.actor_cell_id == self.local_player_cell_id)
+    }
+
+    /// The champion the local player picked or is hovering in a pick action.
+    pub fn my_champion(&self) -> Option<i64> {
+        if let Some(id) = self.me().map(|m| m.champion_id).filter(|id| *id > 0) {
+            return Some(id);
+        }
+
+        self.my_actions()
+            .filter(|a| a.is_pick() && a.champion_id > 0)
+            .map(|a| a.champion_id)
+            .last()
+    }
+
+    pub fn my_assigned_position(&self) -> Option<&str> {
+        self.me()
+            .map(|m| m.assigned_position.as_str())
+            .filter(|p| !p.is_empty())
+    }
+
+    /// The pick or ban the local player has to complete right now.
+    pub fn my_current_action(&self) -> Option<&Action> {
+        self.my_actions()
+            .find(|a| a.is_in_progress && !a.completed && (a.is_pick() || a.is_ban()))
+    }
+
+    pub fn is_my_turn(&self) -> bool {
+        self.my_current_action().is_some()
+    }
+
+    /// Champions banned by either team, including bans not yet revealed in
+    /// `bans` when bans are simultaneous.
+    pub fn banned_champions(&self) -> Vec<i64> {
+        let mut ids: Vec<i64> = self
+            .bans
+            .my_team_bans
+            .iter()
+            .chain(self.bans.their_team_bans.iter())
+            .copied()
+            .chain(
+                self.actions
+                    .iter()
+                    .flatten()
+                    .filter(|a| a.is_ban() && a.completed)
+                    .map(|a| a.champion_id),
+            )
+            .filter(|id| *id > 0)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Champions picked or hovered by the local player's team.
+    pub fn team_picks(&self) -> Vec<i64> {
+        self.my_team
+            .iter()
+            .map(|m| m.champion_id)
+            .filter(|id| *id > 0)
+            .collect()
+    }
+
+    /// Champions picked by the other team, hidden in blind pick.
+    pub fn their_picks(&self) -> Vec<i64> {
+        self.their_team
+            .iter()
+            .map(|m| m.champion_id)
+            .filter(|id| *id > 0)
+            .collect()
+    }
+
+    pub fn bench_champion_ids(&self) -> Vec<i64> {
+        self.bench_champions.iter().map(|b| b.champion_id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(fixture: &str) -> ChampSelectSession {
+        serde_json::from_str(fixture).unwrap()
+    }
+
+    #[test]
+    fn blind_pick_session() {
+        let session = load(include_str!("../fixtures/champ_select/blind_pick.json"));
+
+        assert_eq!(session.me().unwrap().summoner_id, 1003);
+        assert_eq!(session.my_champion(), None);
+        assert_eq!(session.my_assigned_position(), None);
+        assert!(session.is_my_turn());
+        assert_eq!(session.my_current_action().unwrap().id, 3);
+        assert_eq!(session.banned_champions(), Vec::<i64>::new());
+        assert_eq!(session.team_picks(), vec![157]);
+        assert_eq!(session.their_picks(), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn draft_session() {
+        let session = load(include_str!("../fixtures/champ_select/draft.json"));
+
+        assert_eq!(session.my_assigned_position(), Some("jungle"));
+        // hovering in the pick action that is in progress
+        assert_eq!(session.my_champion(), Some(64));
+        assert!(session.is_my_turn());
+        assert!(session.my_current_action().unwrap().is_pick());
+        assert_eq!(session.banned_champions(), vec![84, 238, 555]);
+        assert_eq!(session.team_picks(), vec![266]);
+        assert_eq!(session.their_picks(), vec![122]);
+        assert_eq!(session.timer.phase, "BAN_PICK");
+    }
+
+    #[test]
+    fn aram_session() {
+        let session = load(include_str!("../fixtures/champ_select/aram.json"));
+
+        assert!(session.bench_enabled);
+        assert_eq!(session.my_champion(), Some(18));
+        assert!(!session.is_my_turn());
+        assert_eq!(session.bench_champion_ids(), vec![22, 51, 99]);
+        assert_eq!(session.team_picks(), vec![31, 89, 18, 10, 25]);
+        assert_eq!(session.rerolls_remaining, 1);
+
+        // swapping with the bench only changes our own cell
+        let swapped = load(include_str!("../fixtures/champ_select/aram_bench_swap.json"));
+        assert_eq!(swapped.my_champion(), Some(51));
+        assert_eq!(swapped.bench_champion_ids(), vec![22, 18, 99]);
+        assert_eq!(swapped.team_picks(), vec![31, 89, 51, 10, 25]);
+    }
+
+    #[test]
+    fn custom_game_session() {
+        let session = load(include_str!("../fixtures/champ_select/custom.json"));
+
+        assert!(session.is_custom_game);
+        assert!(session.timer.is_infinite);
+        assert!(session.bench_champions.is_empty());
+        assert_eq!(session.my_champion(), None);
+        assert!(session.is_my_turn());
+    }
+}
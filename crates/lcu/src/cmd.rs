@@ -6,22 +6,39 @@ use std::sync::{Arc, Mutex};
 #[allow(unused_imports)]
 use kv_log_macro::{error, info};
 
+use crate::{
+    discovery_error::DiscoveryError,
+    install_dir::InstallDir,
+    region::Region,
+    watcher::{ClientEvent, ClientWatcher},
+};
+
 const APP_PORT_KEY: &str = "--app-port=";
 const TOKEN_KEY: &str = "--remoting-auth-token=";
 const REGION_KEY: &str = "--region=";
+const LOCALE_KEY: &str = "--locale=";
 const DIR_KEY: &str = "--install-directory=";
+const LOCKFILE_NAME: &str = "lockfile";
+const TENCENT_CLIENT_FOLDER: &str = "LeagueClient";
+const LCU_PROCESS_NAME: &str = "LeagueClientUx";
 #[allow(dead_code)]
-const LCU_COMMAND: &str = "Get-CimInstance Win32_Process -Filter \"name = 'LeagueClientUx.exe'\" | Select-Object -ExpandProperty CommandLine";
+const LCU_COMMAND: &str = "Get-CimInstance Win32_Process -Filter \"name = 'LeagueClientUx.exe'\" | ForEach-Object { \"$($_.ProcessId) $($_.CommandLine)\" }";
+
+#[cfg(target_os = "windows")]
+const DEFAULT_INSTALL_DIRS: &[&str] = &["C:/Riot Games/League of Legends"];
+#[cfg(target_os = "macos")]
+const DEFAULT_INSTALL_DIRS: &[&str] = &["/Applications/League of Legends.app/Contents/LoL"];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const DEFAULT_INSTALL_DIRS: &[&str] = &[];
 
 lazy_static! {
     static ref PORT_REGEXP: regex::Regex = regex::Regex::new(r"--app-port=\d+").unwrap();
     static ref TOKEN_REGEXP: regex::Regex =
         regex::Regex::new(r"--remoting-auth-token=\S+").unwrap();
     static ref REGION_REGEXP: regex::Regex = regex::Regex::new(r"--region=\S+").unwrap();
+    static ref LOCALE_REGEXP: regex::Regex = regex::Regex::new(r"--locale=\S+").unwrap();
     static ref DIR_REGEXP: regex::Regex =
         regex::Regex::new(r#"--install-directory=(.*?)""#).unwrap();
-    static ref MAC_DIR_REGEXP: regex::Regex =
-        regex::Regex::new(r"--install-directory=([^\s]+).*?--").unwrap();
 }
 
 pub fn make_auth_url(token: &String, port: &String) -> String {
@@ -30,15 +47,100 @@ pub fn make_auth_url(token: &String, port: &String) -> String {
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CommandLineOutput {
+    pub pid: u32,
     pub auth_url: String,
-    pub is_tencent: bool,
     pub token: String,
     pub port: String,
+    pub region: Region,
+    pub locale: String,
     pub dir: String,
 }
 
+impl CommandLineOutput {
+    pub fn is_tencent(&self) -> bool {
+        self.region.is_tencent()
+    }
+
+    /// Validates `dir`, falling back to a directory configured by the user.
+    pub fn install_dir(&self, manual: Option<&str>) -> Result<InstallDir, DiscoveryError> {
+        InstallDir::resolve_or_override(&self.dir, &self.region, manual)
+    }
+}
+
+/// Picks one client when several are running.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub enum ClientSelector {
+    #[default]
+    First,
+    Pid(u32),
+    Region(Region),
+}
+
+impl ClientSelector {
+    /// Parses `first`, `pid:<pid>` or `region:<region>`, as set in the
+    /// `CHAMPR_CLIENT` environment variable.
+    pub fn parse(value: &str) -> Option<ClientSelector> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("first") {
+            return Some(ClientSelector::First);
+        }
+
+        let (kind, arg) = value.split_once(':')?;
+        let arg = arg.trim();
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "pid" => arg.parse().ok().map(ClientSelector::Pid),
+            "region" if !arg.is_empty() => Some(ClientSelector::Region(arg.into())),
+            _ => None,
+        }
+    }
+}
+
+pub fn select_client(
+    clients: &[CommandLineOutput],
+    selector: &ClientSelector,
+) -> Option<CommandLineOutput> {
+    clients
+        .iter()
+        .find(|c| match selector {
+            ClientSelector::First => true,
+            ClientSelector::Pid(pid) => c.pid == *pid,
+            ClientSelector::Region(region) => c.region.eq(region),
+        })
+        .cloned()
+}
+
+pub fn get_cmd_output() -> Result<CommandLineOutput, DiscoveryError> {
+    list_clients()?
+        .into_iter()
+        .next()
+        .ok_or(DiscoveryError::ProcessNotFound)
+}
+
+/// Keeps the clients we could parse. When every client fails to parse, the
+/// first error is returned so callers know a client is running.
+fn collect_clients(
+    results: impl Iterator<Item = Result<CommandLineOutput, DiscoveryError>>,
+) -> Result<Vec<CommandLineOutput>, DiscoveryError> {
+    let mut clients = vec![];
+    let mut first_err = None;
+    for result in results {
+        match result {
+            Ok(client) => clients.push(client),
+            Err(e) => {
+                info!("[cmd::list_clients] {}", e);
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+
+    match first_err {
+        Some(e) if clients.is_empty() => Err(e),
+        _ => Ok(clients),
+    }
+}
+
 #[cfg(target_os = "windows")]
-pub fn get_cmd_output() -> Result<CommandLineOutput, ()> {
+pub fn list_clients() -> Result<Vec<CommandLineOutput>, DiscoveryError> {
     use powershell_script::PsScriptBuilder;
 
     let ps = PsScriptBuilder::new()
@@ -54,100 +156,243 @@ pub fn get_cmd_output() -> Result<CommandLineOutput, ()> {
 
             #[cfg(not(debug_assertions))]
             info!("output: {:?}", &output);
-            if output.is_some() {
-                return match_stdout(&String::from(output.unwrap()));
+            if let Some(output) = output {
+                return collect_clients(output.lines().filter_map(|line| {
+                    let (pid, cmd_line) = line.trim().split_once(' ')?;
+                    let pid = pid.parse().ok()?;
+                    Some(match_stdout(cmd_line).map(|c| CommandLineOutput { pid, ..c }))
+                }));
             }
+            Ok(vec![])
+        }
+        Err(err) => {
+            error!("cmd error: {:?}", err);
+            Err(DiscoveryError::CommandFailed(err.to_string()))
         }
-        Err(err) => error!("cmd error: {:?}", err),
     }
+}
 
-    Err(())
+/// A running process and its argument vector, as reported by the OS.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub args: Vec<String>,
+}
+
+pub trait ProcessScanner {
+    fn scan(&self) -> std::io::Result<Vec<ProcessInfo>>;
+}
+
+/// Walks `/proc/*/cmdline`, which keeps every argument intact.
+#[cfg(target_os = "linux")]
+#[derive(Default, Debug, Clone)]
+pub struct ProcScanner;
+
+#[cfg(target_os = "linux")]
+impl ProcessScanner for ProcScanner {
+    fn scan(&self) -> std::io::Result<Vec<ProcessInfo>> {
+        let mut processes = vec![];
+        for entry in std::fs::read_dir("/proc")?.filter_map(Result::ok) {
+            let Some(pid) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            // processes may exit or deny access while we are walking the list
+            let Ok(raw) = std::fs::read(entry.path().join("cmdline")) else {
+                continue;
+            };
+            let args = split_cmdline(&raw);
+            if !args.is_empty() {
+                processes.push(ProcessInfo { pid, args });
+            }
+        }
+
+        Ok(processes)
+    }
+}
+
+/// Lists processes with `ps`, for platforms without `/proc` such as macOS.
+#[cfg(all(not(target_os = "windows"), not(target_os = "linux")))]
+#[derive(Default, Debug, Clone)]
+pub struct PsScanner;
+
+#[cfg(all(not(target_os = "windows"), not(target_os = "linux")))]
+impl ProcessScanner for PsScanner {
+    fn scan(&self) -> std::io::Result<Vec<ProcessInfo>> {
+        use std::process::Command;
+
+        let output = Command::new("ps")
+            .args(["-A", "-ww", "-o", "pid=", "-o", "command="])
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        Ok(stdout
+            .lines()
+            .filter_map(|line| {
+                let (pid, command) = line.trim_start().split_once(' ')?;
+                Some(ProcessInfo {
+                    pid: pid.parse().ok()?,
+                    args: split_flattened_command(command),
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub type NativeScanner = ProcScanner;
+#[cfg(all(not(target_os = "windows"), not(target_os = "linux")))]
+pub type NativeScanner = PsScanner;
+
+/// Returns a fixed process list, for tests.
+#[derive(Default, Debug, Clone)]
+pub struct FakeScanner(pub Vec<ProcessInfo>);
+
+impl ProcessScanner for FakeScanner {
+    fn scan(&self) -> std::io::Result<Vec<ProcessInfo>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Splits the NUL-separated content of `/proc/<pid>/cmdline`.
+pub fn split_cmdline(raw: &[u8]) -> Vec<String> {
+    raw.split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+/// Best effort split of a command line that was flattened into one string,
+/// keeping spaces inside `--key=value` arguments.
+pub fn split_flattened_command(command: &str) -> Vec<String> {
+    let mut parts = command.split(" --");
+    let mut args = vec![];
+    if let Some(program) = parts.next() {
+        args.push(program.trim().to_string());
+    }
+    args.extend(parts.map(|arg| format!("--{}", arg.trim_end())));
+    args
+}
+
+/// Matches the client executable itself, but not helpers such as
+/// `LeagueClientUxRender`.
+fn is_client_program(program: &str) -> bool {
+    let name = program.rsplit(['/', '\\']).next().unwrap_or(program);
+    name.trim_end_matches(".exe").eq(LCU_PROCESS_NAME)
+}
+
+pub fn find_client_processes(
+    scanner: &impl ProcessScanner,
+) -> Result<Vec<ProcessInfo>, DiscoveryError> {
+    Ok(scanner
+        .scan()?
+        .into_iter()
+        .filter(|p| {
+            p.args
+                .first()
+                .is_some_and(|program| is_client_program(program))
+        })
+        .collect())
+}
+
+pub fn list_clients_with(
+    scanner: &impl ProcessScanner,
+) -> Result<Vec<CommandLineOutput>, DiscoveryError> {
+    let processes = find_client_processes(scanner)?;
+    collect_clients(
+        processes
+            .into_iter()
+            .map(|p| match_args(&p.args).map(|c| CommandLineOutput { pid: p.pid, ..c })),
+    )
 }
 
 #[cfg(not(target_os = "windows"))]
-pub fn get_cmd_output() -> Result<CommandLineOutput, ()>{
-    use std::io::{BufRead, BufReader};
-    use std::process::{Command, Stdio};
+pub fn list_clients() -> Result<Vec<CommandLineOutput>, DiscoveryError> {
+    list_clients_with(&NativeScanner::default())
+}
 
-    let cmd_str = r#"ps -A | grep LeagueClientUx | grep remoting-auth-token="#;
-    let mut cmd = Command::new("sh")
-        .args(["-c", cmd_str])
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap();
+fn arg_value<'a>(args: &'a [String], key: &str) -> Option<&'a str> {
+    args.iter()
+        .find_map(|arg| arg.strip_prefix(key))
+        .map(|value| value.trim_matches('"'))
+}
 
-    let mut auth_url = String::new();
-    let mut token = String::new();
-    let mut port = String::new();
-    let mut dir = String::new();
-    let mut is_tencent = false;
+fn parse_region(region: String) -> Result<Region, DiscoveryError> {
+    if region
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
     {
-        let stdout = cmd.stdout.as_mut().unwrap();
-        let stdout_reader = BufReader::new(stdout);
-        let stdout_lines = stdout_reader.lines();
-
-        for line in stdout_lines {
-            match line {
-                Ok(s) => {
-                    if s.contains("--app-port=") {
-                        CommandLineOutput {
-                            auth_url,
-                            is_tencent,
-                            token,
-                            port,
-                            ..
-                        } = match_stdout(&s);
-                        dir = if let Some(dir_match) = MAC_DIR_REGEXP.find(&s) {
-                            dir_match.as_str().replace(DIR_KEY, "").replace(" --", "")
-                        } else {
-                            "".to_string()
-                        };
-                        break;
-                    }
-                }
-                Err(e) => {
-                    info!("[cmd::get_cmd_output] {:?}", e);
-                    return Err(())
-                }
-            }
-        }
+        Ok(Region::from(region))
+    } else {
+        Err(DiscoveryError::MalformedRegion(region))
     }
-    cmd.wait().unwrap();
+}
+
+/// Same as [`match_stdout`], but over an argument vector instead of a
+/// flattened command line.
+pub fn match_args(args: &[String]) -> Result<CommandLineOutput, DiscoveryError> {
+    let port = arg_value(args, APP_PORT_KEY)
+        .filter(|port| port.parse::<u16>().is_ok())
+        .ok_or(DiscoveryError::PortMissing)?
+        .to_string();
+    let token = arg_value(args, TOKEN_KEY)
+        .filter(|token| !token.is_empty())
+        .ok_or(DiscoveryError::TokenMissing)?
+        .to_string();
+    let auth_url = make_auth_url(&token, &port);
+    let region = parse_region(arg_value(args, REGION_KEY).unwrap_or_default().to_string())?;
+    let locale = arg_value(args, LOCALE_KEY).unwrap_or_default().to_string();
+    let dir = arg_value(args, DIR_KEY).unwrap_or_default().to_string();
 
     Ok(CommandLineOutput {
         auth_url,
-        is_tencent,
         token,
         port,
+        region,
+        locale,
         dir,
+        ..Default::default()
     })
 }
 
-pub fn match_stdout(stdout: &str) -> CommandLineOutput {
+pub fn match_stdout(stdout: &str) -> Result<CommandLineOutput, DiscoveryError> {
     let port = if let Some(port_match) = PORT_REGEXP.find(stdout) {
         port_match.as_str().replace(APP_PORT_KEY, "")
     } else {
-        "0".to_string()
+        return Err(DiscoveryError::PortMissing);
     };
-    let token = if let Some(token_match) = TOKEN_REGEXP.find(stdout) {
-        token_match
+    let token = match TOKEN_REGEXP.find(stdout) {
+        Some(token_match) => token_match
             .as_str()
             .replace(TOKEN_KEY, "")
-            .replace(['\\', '\"'], "")
-    } else {
-        "".to_string()
+            .replace(['\\', '\"'], ""),
+        None => String::new(),
     };
+    if token.is_empty() {
+        return Err(DiscoveryError::TokenMissing);
+    }
 
     let auth_url = make_auth_url(&token, &port);
 
-    let is_tencent = if let Some(region_match) = REGION_REGEXP.find(stdout) {
-        let region = region_match
+    let region = if let Some(region_match) = REGION_REGEXP.find(stdout) {
+        region_match
             .as_str()
             .replace(REGION_KEY, "")
-            .replace(['\\', '\"'], "");
-        region.eq("TENCENT")
+            .replace(['\\', '\"'], "")
+    } else {
+        "".to_string()
+    };
+    let region = parse_region(region)?;
+    let locale = if let Some(locale_match) = LOCALE_REGEXP.find(stdout) {
+        locale_match
+            .as_str()
+            .replace(LOCALE_KEY, "")
+            .replace(['\\', '\"'], "")
     } else {
-        false
+        "".to_string()
     };
 
     let raw_dir = if let Some(dir_match) = DIR_REGEXP.find(stdout) {
@@ -155,19 +400,144 @@ pub fn match_stdout(stdout: &str) -> CommandLineOutput {
     } else {
         "".to_string()
     };
-    let output_dir = raw_dir.replace('\"', "");
-    let dir = if is_tencent {
-        format!("{output_dir}/..")
-    } else {
-        format!("{output_dir}/")
-    };
+    let dir = raw_dir.replace('\"', "");
 
-    CommandLineOutput {
+    Ok(CommandLineOutput {
         auth_url,
-        is_tencent,
         token,
         port,
+        region,
+        locale,
         dir,
+        ..Default::default()
+    })
+}
+
+/// Content of the `lockfile` the client writes into its install directory,
+/// formatted as `name:pid:port:password:protocol`.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Lockfile {
+    pub name: String,
+    pub pid: u32,
+    pub port: String,
+    pub password: String,
+    pub protocol: String,
+}
+
+pub fn parse_lockfile(content: &str) -> Option<Lockfile> {
+    let parts: Vec<&str> = content.trim().split(':').collect();
+    if parts.len() != 5 {
+        return None;
+    }
+
+    let pid = parts[1].parse::<u32>().ok()?;
+    let port = parts[2].parse::<u16>().ok()?.to_string();
+    if parts[3].is_empty() {
+        return None;
+    }
+
+    Some(Lockfile {
+        name: parts[0].to_string(),
+        pid,
+        port,
+        password: parts[3].to_string(),
+        protocol: parts[4].to_string(),
+    })
+}
+
+/// Reads the lockfile from a game root. Tencent keeps the client, and its
+/// lockfile, in a `LeagueClient` sub folder.
+pub fn read_lockfile(dir: &str) -> Result<CommandLineOutput, DiscoveryError> {
+    let root = dir.trim_end_matches(['/', '\\']);
+    if std::fs::read_dir(root).is_err() {
+        return Err(DiscoveryError::UnreadableInstallDir(root.to_string()));
+    }
+
+    let candidates = [
+        (format!("{root}/{LOCKFILE_NAME}"), false),
+        (
+            format!("{root}/{TENCENT_CLIENT_FOLDER}/{LOCKFILE_NAME}"),
+            true,
+        ),
+    ];
+
+    for (path, is_tencent) in candidates {
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let Some(lockfile) = parse_lockfile(&content) else {
+            info!("[cmd::read_lockfile] malformed lockfile: {}", &path);
+            continue;
+        };
+
+        // the lockfile has no region, the folder layout is all we know
+        let region = if is_tencent {
+            Region::Tencent
+        } else {
+            Region::default()
+        };
+        return Ok(CommandLineOutput {
+            pid: lockfile.pid,
+            auth_url: make_auth_url(&lockfile.password, &lockfile.port),
+            token: lockfile.password,
+            port: lockfile.port,
+            region,
+            dir: root.to_string(),
+            ..Default::default()
+        });
+    }
+
+    Err(DiscoveryError::ProcessNotFound)
+}
+
+/// Looks for a lockfile in `lol_dir` first, then in the default install
+/// locations of the current platform.
+pub fn get_lockfile_output(lol_dir: Option<&str>) -> Result<CommandLineOutput, DiscoveryError> {
+    let mut result = Err(DiscoveryError::ProcessNotFound);
+    for dir in lol_dir
+        .into_iter()
+        .chain(DEFAULT_INSTALL_DIRS.iter().copied())
+    {
+        match read_lockfile(dir) {
+            Ok(output) => return Ok(output),
+            // an explicitly configured directory we cannot read is worth reporting
+            Err(e) if lol_dir == Some(dir) => result = Err(e),
+            Err(_) => {}
+        }
+    }
+
+    result
+}
+
+/// Discovers the client credentials from the process list, and falls back to
+/// the lockfile when the process list is unavailable or has no client.
+pub fn discover_client(lol_dir: Option<&str>) -> Result<CommandLineOutput, DiscoveryError> {
+    discover_clients_with(list_clients, lol_dir)?
+        .into_iter()
+        .next()
+        .ok_or(DiscoveryError::ProcessNotFound)
+}
+
+/// Same fallback as [`discover_client`], over the clients found by `scan`.
+pub fn discover_clients_with<F>(
+    scan: F,
+    lol_dir: Option<&str>,
+) -> Result<Vec<CommandLineOutput>, DiscoveryError>
+where
+    F: FnOnce() -> Result<Vec<CommandLineOutput>, DiscoveryError>,
+{
+    let e = match scan() {
+        Ok(clients) if !clients.is_empty() => return Ok(clients),
+        Ok(_) => DiscoveryError::ProcessNotFound,
+        Err(e) => e,
+    };
+
+    match get_lockfile_output(lol_dir) {
+        Ok(output) => Ok(vec![output]),
+        Err(_) if e.is_unparseable() => Err(e),
+        Err(DiscoveryError::ProcessNotFound) => Err(e),
+        Err(lockfile_err) => Err(lockfile_err),
     }
 }
 
@@ -197,16 +567,17 @@ pub async fn check_if_server_ready() -> anyhow::Result<bool> {
     use std::os::windows::process::CommandExt;
     use std::process::{Command, Stdio};
 
-    let CommandLineOutput {
-        dir, is_tencent, ..
-    } = get_cmd_output();
-
-    if dir.is_empty() {
-        info!("[cmd::check_if_tencent_server_ready] cannot get lcu install dir");
-        return Ok(false);
-    }
+    let output = get_cmd_output()?;
+    let install_dir = match output.install_dir(None) {
+        Ok(install_dir) => install_dir,
+        Err(e) => {
+            info!("[cmd::check_if_tencent_server_ready] {}", e);
+            return Ok(false);
+        }
+    };
+    let dir = install_dir.root().to_string_lossy().into_owned();
 
-    let is_tencent_arg = if is_tencent { "1" } else { "0" };
+    let is_tencent_arg = if output.is_tencent() { "1" } else { "0" };
     let stdout = Command::new("./LeagueClient.exe")
         .args(["check", &dir, is_tencent_arg])
         .creation_flags(0x08000000)
@@ -232,7 +603,7 @@ pub async fn test_connectivity() -> anyhow::Result<bool> {
     use std::os::windows::process::CommandExt;
     use std::process::{Command, Stdio};
 
-    let CommandLineOutput { port, token, .. } = get_cmd_output();
+    let CommandLineOutput { port, token, .. } = get_cmd_output()?;
 
     let stdout = Command::new("./LeagueClient.exe")
         .args(["test", &token, &port])
@@ -259,19 +630,33 @@ pub async fn test_connectivity() -> anyhow::Result<bool> {
 }
 
 pub fn check_if_lol_running() -> bool {
-    if let Ok(auth) = get_cmd_output() {
-        return !auth.auth_url.is_empty();
-    }
+    matches!(lol_running_status(), Ok(true))
+}
 
-    false
+/// `Ok(false)` when no client is running, `Err` when a client is running but
+/// its credentials cannot be read.
+pub fn lol_running_status() -> Result<bool, DiscoveryError> {
+    match get_cmd_output() {
+        Ok(_) => Ok(true),
+        Err(DiscoveryError::ProcessNotFound) => Ok(false),
+        Err(e) => Err(e),
+    }
 }
 
-pub fn start_check_cmd_task() {}
+/// Watches the client with the default settings, see [`ClientWatcher`].
+pub fn start_check_cmd_task() -> (
+    tokio::task::JoinHandle<()>,
+    tokio::sync::mpsc::UnboundedReceiver<ClientEvent>,
+) {
+    ClientWatcher::default().spawn()
+}
 
-pub fn update_cmd_output_task(output: &Arc<Mutex<CommandLineOutput>>) {
-    if let Ok(result) = get_cmd_output() {
-        *output.lock().unwrap() = result;
-    }
+pub fn update_cmd_output_task(
+    output: &Arc<Mutex<CommandLineOutput>>,
+) -> Result<(), DiscoveryError> {
+    let result = get_cmd_output()?;
+    *output.lock().unwrap() = result;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -283,4 +668,231 @@ mod tests {
         let ret = get_cmd_output();
         println!("{:?}", ret);
     }
+
+    fn client_args(extra: &[&str]) -> Vec<String> {
+        let mut args = vec![
+            "/Applications/League of Legends.app/Contents/LoL/League of Legends.app/Contents/MacOS/LeagueClientUx".to_string(),
+            "--remoting-auth-token=abc123".to_string(),
+            "--app-port=51234".to_string(),
+            "--install-directory=/Applications/League of Legends.app/Contents/LoL".to_string(),
+        ];
+        args.extend(extra.iter().map(|arg| arg.to_string()));
+        args
+    }
+
+    #[test]
+    fn split_proc_cmdline() {
+        let raw = b"/usr/bin/LeagueClientUx\0--app-port=51234\0--install-directory=/opt/League of Legends\0\0";
+        assert_eq!(
+            split_cmdline(raw),
+            vec![
+                "/usr/bin/LeagueClientUx",
+                "--app-port=51234",
+                "--install-directory=/opt/League of Legends",
+            ]
+        );
+    }
+
+    #[test]
+    fn split_flattened_args() {
+        let command = "/Applications/League of Legends.app/LeagueClientUx --app-port=51234 --install-directory=/Applications/League of Legends.app/Contents/LoL --region=NA";
+        assert_eq!(
+            split_flattened_command(command),
+            vec![
+                "/Applications/League of Legends.app/LeagueClientUx",
+                "--app-port=51234",
+                "--install-directory=/Applications/League of Legends.app/Contents/LoL",
+                "--region=NA",
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_with_fake_scanner() {
+        let scanner = FakeScanner(vec![
+            ProcessInfo {
+                pid: 1,
+                args: vec!["/sbin/init".to_string()],
+            },
+            ProcessInfo {
+                pid: 20,
+                args: vec![
+                    "LeagueClientUxRender".to_string(),
+                    "--type=renderer".to_string(),
+                ],
+            },
+            ProcessInfo {
+                pid: 42,
+                args: client_args(&["--region=NA", "--locale=en_US"]),
+            },
+        ]);
+
+        let clients = list_clients_with(&scanner).unwrap();
+        assert_eq!(
+            clients,
+            vec![CommandLineOutput {
+                pid: 42,
+                auth_url: "riot:abc123@127.0.0.1:51234".to_string(),
+                token: "abc123".to_string(),
+                port: "51234".to_string(),
+                region: Region::NA,
+                locale: "en_US".to_string(),
+                dir: "/Applications/League of Legends.app/Contents/LoL".to_string(),
+            }]
+        );
+
+        assert_eq!(list_clients_with(&FakeScanner::default()), Ok(vec![]));
+    }
+
+    #[test]
+    fn select_from_multiple_clients() {
+        let scanner = FakeScanner(vec![
+            ProcessInfo {
+                pid: 42,
+                args: client_args(&["--region=NA"]),
+            },
+            ProcessInfo {
+                pid: 43,
+                args: client_args(&["--region=EUW"]),
+            },
+        ]);
+        let clients = list_clients_with(&scanner).unwrap();
+        assert_eq!(clients.len(), 2);
+
+        let first = select_client(&clients, &ClientSelector::First).unwrap();
+        assert_eq!(first.pid, 42);
+        let by_pid = select_client(&clients, &ClientSelector::Pid(43)).unwrap();
+        assert_eq!(by_pid.region, Region::EUW);
+        let by_region = select_client(&clients, &ClientSelector::Region(Region::NA)).unwrap();
+        assert_eq!(by_region.pid, 42);
+        assert_eq!(select_client(&clients, &ClientSelector::Pid(1)), None);
+    }
+
+    #[test]
+    fn parse_client_selector() {
+        assert_eq!(ClientSelector::parse("first"), Some(ClientSelector::First));
+        assert_eq!(
+            ClientSelector::parse(" PID:4321 "),
+            Some(ClientSelector::Pid(4321))
+        );
+        assert_eq!(
+            ClientSelector::parse("region:EUW"),
+            Some(ClientSelector::Region("EUW".into()))
+        );
+        assert_eq!(ClientSelector::parse("pid:abc"), None);
+        assert_eq!(ClientSelector::parse("region:"), None);
+        assert_eq!(ClientSelector::parse("euw"), None);
+    }
+
+    #[test]
+    fn match_tencent_args() {
+        let output = match_args(&client_args(&["--region=TENCENT"])).unwrap();
+        assert!(output.is_tencent());
+        assert_eq!(
+            output.dir,
+            "/Applications/League of Legends.app/Contents/LoL"
+        );
+    }
+
+    #[test]
+    fn report_unparseable_client() {
+        let no_token = vec!["LeagueClientUx".to_string(), "--app-port=51234".to_string()];
+        assert_eq!(match_args(&no_token), Err(DiscoveryError::TokenMissing));
+
+        let no_port = vec![
+            "LeagueClientUx".to_string(),
+            "--remoting-auth-token=abc123".to_string(),
+        ];
+        assert_eq!(match_args(&no_port), Err(DiscoveryError::PortMissing));
+
+        let bad_region = client_args(&["--region=N/A"]);
+        assert_eq!(
+            match_args(&bad_region),
+            Err(DiscoveryError::MalformedRegion("N/A".to_string()))
+        );
+
+        let scanner = FakeScanner(vec![ProcessInfo {
+            pid: 42,
+            args: no_token,
+        }]);
+        assert_eq!(
+            list_clients_with(&scanner),
+            Err(DiscoveryError::TokenMissing)
+        );
+    }
+
+    #[test]
+    fn match_flattened_stdout() {
+        let stdout = r#""C:/Riot Games/League of Legends/LeagueClientUx.exe" "--remoting-auth-token=abc123" "--app-port=51234" "--region=EUW" "--install-directory=C:/Riot Games/League of Legends""#;
+        let output = match_stdout(stdout).unwrap();
+        assert_eq!(output.port, "51234");
+        assert_eq!(output.token, "abc123");
+        assert_eq!(output.region, Region::EUW);
+        assert_eq!(output.dir, "C:/Riot Games/League of Legends");
+
+        assert_eq!(
+            match_stdout(r#""LeagueClientUx.exe" "--remoting-auth-token=abc123""#),
+            Err(DiscoveryError::PortMissing)
+        );
+    }
+
+    #[test]
+    fn parse_valid_lockfile() {
+        let lockfile = parse_lockfile("LeagueClient:12345:54321:s3cr3t-T0ken:https\n").unwrap();
+        assert_eq!(
+            lockfile,
+            Lockfile {
+                name: "LeagueClient".to_string(),
+                pid: 12345,
+                port: "54321".to_string(),
+                password: "s3cr3t-T0ken".to_string(),
+                protocol: "https".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_invalid_lockfile() {
+        assert_eq!(parse_lockfile(""), None);
+        assert_eq!(parse_lockfile("LeagueClient:12345:54321:https"), None);
+        assert_eq!(parse_lockfile("LeagueClient:pid:54321:token:https"), None);
+        assert_eq!(parse_lockfile("LeagueClient:12345:99999:token:https"), None);
+        assert_eq!(parse_lockfile("LeagueClient:12345:54321::https"), None);
+    }
+
+    #[test]
+    fn read_lockfile_from_dir() {
+        let root = std::env::temp_dir().join(format!("champr_lockfile_{}", nanoid::nanoid!(6)));
+        let tencent_root = root.join("tencent");
+        std::fs::create_dir_all(tencent_root.join(TENCENT_CLIENT_FOLDER)).unwrap();
+        std::fs::write(
+            root.join(LOCKFILE_NAME),
+            "LeagueClient:1:2999:riot-token:https",
+        )
+        .unwrap();
+        std::fs::write(
+            tencent_root.join(TENCENT_CLIENT_FOLDER).join(LOCKFILE_NAME),
+            "LeagueClient:2:3999:tencent-token:https",
+        )
+        .unwrap();
+
+        let riot = read_lockfile(root.to_str().unwrap()).unwrap();
+        assert_eq!(riot.port, "2999");
+        assert_eq!(riot.token, "riot-token");
+        assert_eq!(riot.auth_url, "riot:riot-token@127.0.0.1:2999");
+        assert!(!riot.is_tencent());
+
+        let tencent = read_lockfile(tencent_root.to_str().unwrap()).unwrap();
+        assert_eq!(tencent.token, "tencent-token");
+        assert!(tencent.is_tencent());
+
+        let missing = root.join("missing");
+        assert_eq!(
+            read_lockfile(missing.to_str().unwrap()),
+            Err(DiscoveryError::UnreadableInstallDir(
+                missing.to_str().unwrap().to_string()
+            ))
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
 }
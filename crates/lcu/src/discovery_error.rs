@@ -0,0 +1,57 @@
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiscoveryError {
+    ProcessNotFound,
+    PermissionDenied(String),
+    CommandFailed(String),
+    PortMissing,
+    TokenMissing,
+    MalformedRegion(String),
+    UnreadableInstallDir(String),
+}
+
+impl DiscoveryError {
+    /// The client is running, but we failed to read its credentials.
+    pub fn is_unparseable(&self) -> bool {
+        matches!(
+            self,
+            DiscoveryError::PortMissing
+                | DiscoveryError::TokenMissing
+                | DiscoveryError::MalformedRegion(_)
+        )
+    }
+}
+
+impl fmt::Display for DiscoveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiscoveryError::ProcessNotFound => write!(f, "league client is not running"),
+            DiscoveryError::PermissionDenied(msg) => {
+                write!(f, "permission denied when listing processes: {msg}")
+            }
+            DiscoveryError::CommandFailed(msg) => write!(f, "failed to list processes: {msg}"),
+            DiscoveryError::PortMissing => write!(f, "league client has no `--app-port`"),
+            DiscoveryError::TokenMissing => {
+                write!(f, "league client has no `--remoting-auth-token`")
+            }
+            DiscoveryError::MalformedRegion(region) => write!(f, "malformed region: {region:?}"),
+            DiscoveryError::UnreadableInstallDir(dir) => {
+                write!(f, "cannot read install directory: {dir}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DiscoveryError {}
+
+impl From<std::io::Error> for DiscoveryError {
+    fn from(error: std::io::Error) -> DiscoveryError {
+        match error.kind() {
+            std::io::ErrorKind::PermissionDenied => {
+                DiscoveryError::PermissionDenied(error.to_string())
+            }
+            _ => DiscoveryError::CommandFailed(error.to_string()),
+        }
+    }
+}
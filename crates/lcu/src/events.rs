@@ -0,0 +1,803 @@
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures::{channel::mpsc, SinkExt, Stream, StreamExt};
+use kv_log_macro::{info, warn};
+use reqwest_websocket::{Message, WebSocket};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::{
+    cmd::{self, CommandLineOutput},
+    discovery_error::DiscoveryError,
+    lcu_api::LcuClient,
+    lcu_error::LcuError,
+};
+
+const OPCODE_SUBSCRIBE: u8 = 5;
+const OPCODE_UNSUBSCRIBE: u8 = 6;
+const OPCODE_EVENT: u8 = 8;
+/// The WAMP topic carrying every JSON API event.
+pub const JSON_API_EVENT: &str = "OnJsonApiEvent";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventType {
+    Create,
+    Update,
+    Delete,
+}
+
+/// Payload of a WAMP `[8, topic, event]` message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LcuEvent {
+    pub uri: String,
+    pub event_type: EventType,
+    #[serde(default)]
+    pub data: Value,
+}
+
+impl LcuEvent {
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<T, LcuError> {
+        Ok(serde_path_to_error::deserialize(&self.data)?)
+    }
+}
+
+fn wamp_message(opcode: u8, topic: &str) -> Message {
+    Message::Text(serde_json::json!([opcode, topic]).to_string())
+}
+
+/// Topic and event of a WAMP event message, `None` for anything else, e.g.
+/// the WAMP welcome.
+pub fn decode_event(text: &str) -> Option<(String, LcuEvent)> {
+    let (opcode, topic, event): (u8, String, LcuEvent) = serde_json::from_str(text).ok()?;
+    (opcode == OPCODE_EVENT).then_some((topic, event))
+}
+
+/// `/lol-champ-select/v1` matches itself and everything below it, but not
+/// `/lol-champ-select/v1-foo`.
+pub fn matches_prefix(uri_prefix: &str, uri: &str) -> bool {
+    let prefix = uri_prefix.trim_end_matches('/');
+    match uri.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+// sent once per connection change, boxing the client is not worth it
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum ConnectionEvent {
+    /// The websocket is up and all subscriptions are in place.
+    Connected(LcuClient),
+    Disconnected,
+}
+
+/// What the automations on top of the event client follow, e.g.
+/// [`GameflowTracker::follow`]. Tests and recordings feed them directly.
+///
+/// [`GameflowTracker::follow`]: crate::gameflow::GameflowTracker::follow
+#[derive(Debug, Clone)]
+pub enum EventInput {
+    Event(LcuEvent),
+    Connection(ConnectionEvent),
+}
+
+enum Command {
+    Subscribe {
+        id: u64,
+        uri_prefix: String,
+        events: mpsc::UnboundedSender<LcuEvent>,
+    },
+    Unsubscribe(u64),
+    WatchConnection(mpsc::UnboundedSender<ConnectionEvent>),
+}
+
+struct Subscriber {
+    id: u64,
+    uri_prefix: String,
+    events: mpsc::UnboundedSender<LcuEvent>,
+}
+
+/// Fans events out to subscribers by URI prefix. The websocket itself only
+/// subscribes to `OnJsonApiEvent`, which carries every event, as long as
+/// anyone listens. It outlives a single connection.
+#[derive(Default)]
+struct Router {
+    subscribers: Vec<Subscriber>,
+    connection: Option<LcuClient>,
+    watchers: Vec<mpsc::UnboundedSender<ConnectionEvent>>,
+}
+
+impl Router {
+    /// Applies `command`, returns the message to send if we are connected.
+    fn handle(&mut self, command: Command) -> Option<Message> {
+        match command {
+            Command::Subscribe {
+                id,
+                uri_prefix,
+                events,
+            } => self
+                .add(Subscriber {
+                    id,
+                    uri_prefix,
+                    events,
+                })
+                .then(|| wamp_message(OPCODE_SUBSCRIBE, JSON_API_EVENT)),
+            Command::Unsubscribe(id) => self
+                .remove(id)
+                .then(|| wamp_message(OPCODE_UNSUBSCRIBE, JSON_API_EVENT)),
+            Command::WatchConnection(watcher) => {
+                if let Some(client) = &self.connection {
+                    let _ = watcher.unbounded_send(ConnectionEvent::Connected(client.clone()));
+                }
+                self.watchers.push(watcher);
+                None
+            }
+        }
+        .filter(|_| self.connection.is_some())
+    }
+
+    /// Whether to subscribe to `OnJsonApiEvent` after connecting.
+    fn is_subscribed(&self) -> bool {
+        !self.subscribers.is_empty()
+    }
+
+    fn set_connection(&mut self, connection: Option<LcuClient>) {
+        let event = match &connection {
+            Some(client) => ConnectionEvent::Connected(client.clone()),
+            None if self.connection.is_some() => ConnectionEvent::Disconnected,
+            None => return,
+        };
+        self.connection = connection;
+        self.watchers
+            .retain(|w| w.unbounded_send(event.clone()).is_ok());
+    }
+
+    /// `true` for the first subscriber, which needs the websocket subscription.
+    fn add(&mut self, subscriber: Subscriber) -> bool {
+        let is_first = !self.is_subscribed();
+        self.subscribers.push(subscriber);
+        is_first
+    }
+
+    /// `true` once the last subscriber is gone.
+    fn remove(&mut self, id: u64) -> bool {
+        let Some(index) = self.subscribers.iter().position(|s| s.id == id) else {
+            return false;
+        };
+        self.subscribers.remove(index);
+        !self.is_subscribed()
+    }
+
+    /// Events of any other topic are ignored, we never subscribe to them.
+    fn dispatch(&self, topic: &str, event: &LcuEvent) {
+        if topic != JSON_API_EVENT {
+            return;
+        }
+        for subscriber in &self.subscribers {
+            if matches_prefix(&subscriber.uri_prefix, &event.uri) {
+                let _ = subscriber.events.unbounded_send(event.clone());
+            }
+        }
+    }
+}
+
+/// Events of one URI prefix. Dropping it unsubscribes.
+pub struct Subscription {
+    id: u64,
+    events: mpsc::UnboundedReceiver<LcuEvent>,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl Stream for Subscription {
+    type Item = LcuEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<LcuEvent>> {
+        self.events.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let _ = self.commands.unbounded_send(Command::Unsubscribe(self.id));
+    }
+}
+
+pub type CredentialsFn = Arc<dyn Fn() -> Result<CommandLineOutput, DiscoveryError> + Send + Sync>;
+
+/// Reconnect policy of [`LcuEventClient::spawn`].
+///
+/// Every attempt reads fresh credentials, as a restarted client listens on
+/// a new port with a new token. The delay between failed attempts doubles
+/// up to `max_backoff`.
+#[derive(Clone)]
+pub struct Reconnect {
+    min_backoff: Duration,
+    max_backoff: Duration,
+    credentials: CredentialsFn,
+}
+
+impl Default for Reconnect {
+    fn default() -> Self {
+        Self {
+            min_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            credentials: Arc::new(|| cmd::discover_client(None)),
+        }
+    }
+}
+
+impl Reconnect {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = max.max(min);
+        self
+    }
+
+    /// Replaces the process and lockfile discovery, e.g. with a fake in tests.
+    pub fn credentials_with<F>(mut self, credentials: F) -> Self
+    where
+        F: Fn() -> Result<CommandLineOutput, DiscoveryError> + Send + Sync + 'static,
+    {
+        self.credentials = Arc::new(credentials);
+        self
+    }
+
+    fn next_backoff(&self, backoff: Duration) -> Duration {
+        (backoff * 2).min(self.max_backoff)
+    }
+
+    async fn connect(&self) -> Option<(LcuClient, WebSocket)> {
+        let credentials = self.credentials.clone();
+        let output = match tokio::task::spawn_blocking(move || credentials()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                info!("[events] no client to connect to: {}", e);
+                return None;
+            }
+            Err(e) => {
+                warn!("[events] discovery task failed: {:?}", e);
+                return None;
+            }
+        };
+
+        let client = match LcuClient::from_cmd_output(&output) {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("[events] {}", e);
+                return None;
+            }
+        };
+        match client.connect_websocket().await {
+            Ok(ws) => Some((client, ws)),
+            Err(e) => {
+                warn!("[events] error connecting websocket: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// WAMP client of the LCU websocket.
+///
+/// The connection runs in a background task which ends once the client and
+/// all its subscriptions are dropped. Subscriptions survive reconnects.
+pub struct LcuEventClient {
+    next_id: AtomicU64,
+    commands: mpsc::UnboundedSender<Command>,
+    task: JoinHandle<()>,
+}
+
+impl LcuEventClient {
+    /// Connects to `client` once, subscriptions end with the websocket.
+    pub async fn connect(client: &LcuClient) -> Result<Self, reqwest_websocket::Error> {
+        let mut ws = client.connect_websocket().await?;
+        let client = client.clone();
+        Ok(Self::start(|mut commands| async move {
+            let mut router = Router::default();
+            session(client, &mut ws, &mut router, &mut commands).await;
+            router.set_connection(None);
+        }))
+    }
+
+    /// Keeps connecting to the running client in the background, see
+    /// [`Reconnect`].
+    pub fn spawn(reconnect: Reconnect) -> Self {
+        Self::start(|commands| run_reconnecting(reconnect, commands))
+    }
+
+    fn start<F, Fut>(run: F) -> Self
+    where
+        F: FnOnce(mpsc::UnboundedReceiver<Command>) -> Fut,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let (commands, rx) = mpsc::unbounded();
+        Self {
+            next_id: AtomicU64::new(0),
+            commands,
+            task: tokio::spawn(run(rx)),
+        }
+    }
+
+    /// Events whose uri is `uri_prefix` or below it, an empty prefix
+    /// receives every event.
+    pub fn subscribe(&self, uri_prefix: &str) -> Subscription {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, events) = mpsc::unbounded();
+        let _ = self.commands.unbounded_send(Command::Subscribe {
+            id,
+            uri_prefix: uri_prefix.to_string(),
+            events: tx,
+        });
+
+        Subscription {
+            id,
+            events,
+            commands: self.commands.clone(),
+        }
+    }
+
+    /// Connection changes, starting with `Connected` if we are connected
+    /// already.
+    pub fn connection_events(&self) -> mpsc::UnboundedReceiver<ConnectionEvent> {
+        let (tx, rx) = mpsc::unbounded();
+        let _ = self.commands.unbounded_send(Command::WatchConnection(tx));
+        rx
+    }
+
+    /// Events below `uri_prefix` merged with the connection changes.
+    pub fn inputs(&self, uri_prefix: &str) -> impl Stream<Item = EventInput> + Unpin + Send + 'static {
+        let events = self.subscribe(uri_prefix).map(EventInput::Event);
+        let connection = self.connection_events().map(EventInput::Connection);
+        futures::stream::select(events, connection)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+enum SessionEnd {
+    Disconnected,
+    /// The client and all subscriptions were dropped.
+    Finished,
+}
+
+async fn run_reconnecting(reconnect: Reconnect, mut commands: mpsc::UnboundedReceiver<Command>) {
+    let mut router = Router::default();
+    let mut backoff = reconnect.min_backoff;
+
+    loop {
+        if let Some((client, mut ws)) = reconnect.connect().await {
+            backoff = reconnect.min_backoff;
+            let end = session(client, &mut ws, &mut router, &mut commands).await;
+            router.set_connection(None);
+            if let SessionEnd::Finished = end {
+                return;
+            }
+        }
+
+        if !wait_disconnected(backoff, &mut router, &mut commands).await {
+            return;
+        }
+        backoff = reconnect.next_backoff(backoff);
+    }
+}
+
+/// Keeps track of subscriptions while there is no websocket, `false` once
+/// no one listens anymore.
+async fn wait_disconnected(
+    duration: Duration,
+    router: &mut Router,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+) -> bool {
+    let sleep = tokio::time::sleep(duration);
+    tokio::pin!(sleep);
+
+    loop {
+        tokio::select! {
+            _ = &mut sleep => return true,
+            command = commands.next() => match command {
+                Some(command) => {
+                    router.handle(command);
+                }
+                None => return false,
+            },
+        }
+    }
+}
+
+/// Replays the subscriptions of `router` and forwards events until the
+/// websocket closes.
+async fn session(
+    client: LcuClient,
+    ws: &mut WebSocket,
+    router: &mut Router,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+) -> SessionEnd {
+    if router.is_subscribed() {
+        if let Err(e) = ws.send(wamp_message(OPCODE_SUBSCRIBE, JSON_API_EVENT)).await {
+            warn!("[events] error subscribing to {}: {}", JSON_API_EVENT, e);
+            return SessionEnd::Disconnected;
+        }
+    }
+    info!("[events] connected to {}", client.base_url());
+    router.set_connection(Some(client));
+
+    loop {
+        tokio::select! {
+            command = commands.next() => {
+                let Some(command) = command else {
+                    return SessionEnd::Finished;
+                };
+                if let Some(message) = router.handle(command) {
+                    if let Err(e) = ws.send(message).await {
+                        warn!("[events] error sending message: {}", e);
+                        return SessionEnd::Disconnected;
+                    }
+                }
+            }
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    if let Some((topic, event)) = decode_event(&text) {
+                        router.dispatch(&topic, &event);
+                    }
+                }
+                Some(Ok(Message::Close { .. })) | None => {
+                    info!("[events] websocket closed");
+                    return SessionEnd::Disconnected;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    warn!("[events] error receiving message: {}", e);
+                    return SessionEnd::Disconnected;
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockLcu;
+    use serde_json::json;
+
+    fn event(uri: &str) -> LcuEvent {
+        LcuEvent {
+            uri: uri.to_string(),
+            event_type: EventType::Update,
+            data: Value::Null,
+        }
+    }
+
+    fn text(message: Option<Message>) -> Option<String> {
+        match message? {
+            Message::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn subscriber(id: u64, uri_prefix: &str) -> (Subscriber, mpsc::UnboundedReceiver<LcuEvent>) {
+        let (events, rx) = mpsc::unbounded();
+        let subscriber = Subscriber {
+            id,
+            uri_prefix: uri_prefix.to_string(),
+            events,
+        };
+        (subscriber, rx)
+    }
+
+    #[test]
+    fn wamp_messages() {
+        assert_eq!(
+            text(Some(wamp_message(OPCODE_SUBSCRIBE, "OnJsonApiEvent"))).unwrap(),
+            r#"[5,"OnJsonApiEvent"]"#
+        );
+    }
+
+    #[test]
+    fn decode_events() {
+        let text = r#"[8,"OnJsonApiEvent",{"data":{"championId":103},"eventType":"Update","uri":"/lol-champ-select/v1/summoners/0"}]"#;
+        let (topic, event) = decode_event(text).unwrap();
+        assert_eq!(topic, "OnJsonApiEvent");
+        assert_eq!(event.uri, "/lol-champ-select/v1/summoners/0");
+        assert_eq!(event.event_type, EventType::Update);
+        assert_eq!(event.data["championId"], 103);
+
+        let text = r#"[8,"OnJsonApiEvent",{"data":null,"eventType":"Delete","uri":"/lol-champ-select/v1/session"}]"#;
+        assert_eq!(decode_event(text).unwrap().1.event_type, EventType::Delete);
+
+        assert_eq!(decode_event(r#"[0,"abc",1,"server"]"#), None);
+        assert_eq!(decode_event(""), None);
+    }
+
+    #[test]
+    fn match_uri_prefix() {
+        assert!(matches_prefix("", "/lol-gameflow/v1/gameflow-phase"));
+        assert!(matches_prefix(
+            "/lol-champ-select/v1",
+            "/lol-champ-select/v1/session"
+        ));
+        assert!(matches_prefix(
+            "/lol-champ-select/v1/",
+            "/lol-champ-select/v1"
+        ));
+        assert!(!matches_prefix(
+            "/lol-champ-select/v1",
+            "/lol-champ-select/v1-legacy"
+        ));
+        assert!(!matches_prefix(
+            "/lol-champ-select/v1/session",
+            "/lol-champ-select/v1"
+        ));
+    }
+
+    #[test]
+    fn route_events_by_prefix() {
+        let mut router = Router::default();
+        let (all, mut all_rx) = subscriber(0, "");
+        let (session, mut session_rx) = subscriber(1, "/lol-champ-select/v1/session");
+        let (session2, mut session2_rx) = subscriber(2, "/lol-champ-select/v1/session");
+
+        // one websocket subscription serves everyone
+        assert!(router.add(all));
+        assert!(!router.add(session));
+        assert!(!router.add(session2));
+
+        let uri = "/lol-champ-select/v1/session";
+        router.dispatch("OnJsonApiEvent", &event(uri));
+        router.dispatch("OnJsonApiEvent", &event("/lol-gameflow/v1/gameflow-phase"));
+        // we never subscribe to other topics
+        router.dispatch("OnJsonApiEvent_lol-champ-select_v1_session", &event(uri));
+        assert_eq!(all_rx.try_next().unwrap().unwrap().uri, uri);
+        assert_eq!(
+            all_rx.try_next().unwrap().unwrap().uri,
+            "/lol-gameflow/v1/gameflow-phase"
+        );
+        assert!(all_rx.try_next().is_err());
+        assert_eq!(session_rx.try_next().unwrap().unwrap().uri, uri);
+        assert!(session_rx.try_next().is_err());
+        assert_eq!(session2_rx.try_next().unwrap().unwrap().uri, uri);
+
+        assert!(!router.remove(1));
+        assert!(!router.remove(2));
+        assert!(router.remove(0));
+        assert!(!router.remove(0));
+    }
+
+    #[test]
+    fn track_connection() {
+        let mut router = Router::default();
+        let (early, mut early_rx) = mpsc::unbounded();
+        assert!(router.handle(Command::WatchConnection(early)).is_none());
+
+        let (a, _a_rx) = subscriber(0, "/lol-gameflow/v1/gameflow-phase");
+        let (b, _b_rx) = subscriber(1, "/lol-champ-select/v1");
+        let (c, _c_rx) = subscriber(2, "/lol-champ-select/v1/");
+        let subscribe = |s: Subscriber| Command::Subscribe {
+            id: s.id,
+            uri_prefix: s.uri_prefix,
+            events: s.events,
+        };
+        // nothing is sent while disconnected, the subscription is replayed later
+        assert!(router.handle(subscribe(a)).is_none());
+        assert!(router.handle(subscribe(b)).is_none());
+        assert!(router.is_subscribed());
+        assert!(early_rx.try_next().is_err());
+
+        router.set_connection(Some(LcuClient::new("51234", "abc123").unwrap()));
+        assert!(matches!(
+            early_rx.try_next(),
+            Ok(Some(ConnectionEvent::Connected(_)))
+        ));
+        assert!(router.handle(subscribe(c)).is_none());
+        assert!(router.handle(Command::Unsubscribe(0)).is_none());
+        assert!(router.handle(Command::Unsubscribe(1)).is_none());
+        assert_eq!(
+            text(router.handle(Command::Unsubscribe(2))).unwrap(),
+            r#"[6,"OnJsonApiEvent"]"#
+        );
+
+        // a late watcher learns about the current connection
+        let (late, mut late_rx) = mpsc::unbounded();
+        router.handle(Command::WatchConnection(late));
+        assert!(matches!(
+            late_rx.try_next(),
+            Ok(Some(ConnectionEvent::Connected(_)))
+        ));
+
+        router.set_connection(None);
+        router.set_connection(None);
+        assert!(matches!(
+            early_rx.try_next(),
+            Ok(Some(ConnectionEvent::Disconnected))
+        ));
+        assert!(early_rx.try_next().is_err());
+        assert!(matches!(
+            late_rx.try_next(),
+            Ok(Some(ConnectionEvent::Disconnected))
+        ));
+    }
+
+    #[test]
+    fn exponential_backoff() {
+        let reconnect =
+            Reconnect::new().backoff(Duration::from_millis(100), Duration::from_millis(350));
+        assert_eq!(
+            reconnect.next_backoff(Duration::from_millis(100)),
+            Duration::from_millis(200)
+        );
+        assert_eq!(
+            reconnect.next_backoff(Duration::from_millis(200)),
+            Duration::from_millis(350)
+        );
+        assert_eq!(
+            reconnect.next_backoff(Duration::from_millis(350)),
+            Duration::from_millis(350)
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_until_dropped() {
+        let attempts = Arc::new(AtomicU64::new(0));
+        let counter = attempts.clone();
+        let reconnect = Reconnect::new()
+            .backoff(Duration::from_millis(5), Duration::from_millis(10))
+            .credentials_with(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Err(DiscoveryError::ProcessNotFound)
+            });
+
+        let client = LcuEventClient::spawn(reconnect);
+        let subscription = client.subscribe("/lol-gameflow/v1/gameflow-phase");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(attempts.load(Ordering::SeqCst) > 2);
+        assert!(!client.is_finished());
+
+        let task = client.task;
+        drop(client.commands);
+        drop(subscription);
+        tokio::time::timeout(Duration::from_secs(2), task)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    async fn recv<T>(rx: &mut (impl Stream<Item = T> + Unpin)) -> T {
+        tokio::time::timeout(Duration::from_secs(5), rx.next())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    async fn wait_unsubscribed(mock: &MockLcu) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !mock.subscriptions().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn receive_events_from_mock() {
+        tokio::time::timeout(Duration::from_secs(20), async {
+            let mock = MockLcu::start().await;
+            let client = LcuEventClient::connect(&mock.client()).await.unwrap();
+            let mut session = client.subscribe("/lol-champ-select/v1/session");
+            let mut summoner = client.subscribe("/lol-champ-select/v1/summoners/0");
+            let mut connection = client.connection_events();
+            assert!(matches!(
+                recv(&mut connection).await,
+                ConnectionEvent::Connected(_)
+            ));
+            mock.wait_for_subscription("OnJsonApiEvent").await;
+
+            mock.emit(
+                "/lol-gameflow/v1/gameflow-phase",
+                EventType::Update,
+                json!("Lobby"),
+            );
+            mock.emit(
+                "/lol-champ-select/v1/session",
+                EventType::Create,
+                json!({ "localPlayerCellId": 2 }),
+            );
+            mock.emit(
+                "/lol-champ-select/v1/summoners/0",
+                EventType::Update,
+                json!({ "championId": 103 }),
+            );
+            let event = recv(&mut session).await;
+            assert_eq!(event.event_type, EventType::Create);
+            assert_eq!(event.data["localPlayerCellId"], 2);
+            let event = recv(&mut summoner).await;
+            assert_eq!(event.data["championId"], 103);
+            assert!(session.events.try_next().is_err());
+
+            drop(session);
+            drop(summoner);
+            wait_unsubscribed(&mock).await;
+
+            mock.close_websockets();
+            assert!(matches!(
+                recv(&mut connection).await,
+                ConnectionEvent::Disconnected
+            ));
+        })
+        .await
+        .expect("websocket test timed out");
+    }
+
+    #[tokio::test]
+    async fn resubscribe_after_reconnect() {
+        tokio::time::timeout(Duration::from_secs(20), async {
+            let mock = MockLcu::start().await;
+            let output = mock.cmd_output();
+            let reconnect = Reconnect::new()
+                .backoff(Duration::from_millis(10), Duration::from_millis(50))
+                .credentials_with(move || Ok(output.clone()));
+            let client = LcuEventClient::spawn(reconnect);
+            let mut phases = client.subscribe("/lol-gameflow/v1/gameflow-phase");
+            let mut connection = client.connection_events();
+            let topic = "OnJsonApiEvent";
+
+            assert!(matches!(
+                recv(&mut connection).await,
+                ConnectionEvent::Connected(_)
+            ));
+            mock.wait_for_subscription(topic).await;
+            mock.close_websockets();
+            assert!(matches!(
+                recv(&mut connection).await,
+                ConnectionEvent::Disconnected
+            ));
+            wait_unsubscribed(&mock).await;
+
+            assert!(matches!(
+                recv(&mut connection).await,
+                ConnectionEvent::Connected(_)
+            ));
+            mock.wait_for_subscription(topic).await;
+            mock.emit(
+                "/lol-gameflow/v1/gameflow-phase",
+                EventType::Update,
+                json!("Lobby"),
+            );
+            assert_eq!(recv(&mut phases).await.data, json!("Lobby"));
+        })
+        .await
+        .expect("reconnecting timed out");
+    }
+
+    #[test]
+    fn decode_event_data() {
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Summoner {
+            champion_id: i64,
+        }
+
+        let mut event = event("/lol-champ-select/v1/summoners/0");
+        event.data = serde_json::json!({ "championId": 103 });
+        assert_eq!(event.data_as::<Summoner>().unwrap().champion_id, 103);
+
+        event.data = Value::Null;
+        assert!(event.data_as::<Summoner>().is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{gameflow::GameflowPhase, source::SourceItem};
+
+const SUMMONERS_RIFT: i64 = 11;
+const HOWLING_ABYSS: i64 = 12;
+
+/// What kind of builds the current game needs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// Summoner's Rift, including normal, ranked and custom games.
+    #[default]
+    Classic,
+    Aram,
+    Urf,
+    /// 2v2v2v2, `CHERRY` in the client.
+    Arena,
+    Other(String),
+}
+
+impl GameMode {
+    /// Maps the queue, map and `gameMode` of a lobby or game. The queue wins
+    /// as rotating modes share maps, e.g. ARURF is played on Summoner's Rift.
+    pub fn from_queue(queue_id: i64, map_id: i64, game_mode: &str) -> GameMode {
+        match queue_id {
+            100 | 450 | 720 | 2400 => return GameMode::Aram,
+            900 | 1010 | 1900 => return GameMode::Urf,
+            1700 | 1710 => return GameMode::Arena,
+            _ => {}
+        }
+
+        match game_mode.to_ascii_uppercase().as_str() {
+            "ARAM" | "KINGPORO" => GameMode::Aram,
+            "URF" | "ARURF" => GameMode::Urf,
+            "CHERRY" => GameMode::Arena,
+            "CLASSIC" | "PRACTICETOOL" => GameMode::Classic,
+            "" if map_id == HOWLING_ABYSS => GameMode::Aram,
+            "" if map_id == SUMMONERS_RIFT || map_id == 0 => GameMode::Classic,
+            _ => GameMode::Other(game_mode.to_string()),
+        }
+    }
+
+    /// Whether `source` has builds for this mode. Modes without dedicated
+    /// sources use the Summoner's Rift ones.
+    pub fn supports(&self, source: &SourceItem) -> bool {
+        let is_aram = source.is_aram.unwrap_or_default();
+        let is_urf = source.is_urf.unwrap_or_default();
+        match self {
+            GameMode::Aram => is_aram,
+            GameMode::Urf => is_urf,
+            GameMode::Classic | GameMode::Arena | GameMode::Other(_) => !is_aram && !is_urf,
+        }
+    }
+
+    pub fn filter_sources<'a>(&self, sources: &'a [SourceItem]) -> Vec<&'a SourceItem> {
+        sources.iter().filter(|s| self.supports(s)).collect()
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Queue {
+    pub id: i64,
+    pub map_id: i64,
+    pub game_mode: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GameData {
+    pub game_id: i64,
+    pub queue: Queue,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GameMap {
+    pub id: i64,
+    pub game_mode: String,
+}
+
+/// `/lol-gameflow/v1/session`
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GameflowSession {
+    pub phase: GameflowPhase,
+    pub game_data: GameData,
+    pub map: GameMap,
+}
+
+impl GameflowSession {
+    pub fn game_mode(&self) -> GameMode {
+        let queue = &self.game_data.queue;
+        let game_mode = if queue.game_mode.is_empty() {
+            &self.map.game_mode
+        } else {
+            &queue.game_mode
+        };
+        let map_id = if queue.map_id > 0 {
+            queue.map_id
+        } else {
+            self.map.id
+        };
+        GameMode::from_queue(queue.id, map_id, game_mode)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GameConfig {
+    pub queue_id: i64,
+    pub map_id: i64,
+    pub game_mode: String,
+    pub is_custom: bool,
+}
+
+/// `/lol-lobby/v2/lobby`
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Lobby {
+    pub game_config: GameConfig,
+}
+
+impl Lobby {
+    pub fn game_mode(&self) -> GameMode {
+        let config = &self.game_config;
+        GameMode::from_queue(config.queue_id, config.map_id, &config.game_mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(value: &str, is_aram: Option<bool>, is_urf: Option<bool>) -> SourceItem {
+        SourceItem {
+            label: value.to_string(),
+            value: value.to_string(),
+            is_aram,
+            is_urf,
+        }
+    }
+
+    #[test]
+    fn map_queue_to_mode() {
+        assert_eq!(GameMode::from_queue(420, 11, "CLASSIC"), GameMode::Classic);
+        assert_eq!(GameMode::from_queue(450, 12, "ARAM"), GameMode::Aram);
+        assert_eq!(GameMode::from_queue(900, 11, "URF"), GameMode::Urf);
+        assert_eq!(GameMode::from_queue(1700, 30, "CHERRY"), GameMode::Arena);
+        // custom games have no queue
+        assert_eq!(GameMode::from_queue(-1, 12, "ARAM"), GameMode::Aram);
+        assert_eq!(GameMode::from_queue(-1, 12, ""), GameMode::Aram);
+        assert_eq!(
+            GameMode::from_queue(-1, 21, "NEXUSBLITZ"),
+            GameMode::Other("NEXUSBLITZ".to_string())
+        );
+    }
+
+    #[test]
+    fn read_mode_from_session() {
+        let session: GameflowSession = serde_json::from_str(
+            r#"{"phase":"ChampSelect","gameData":{"gameId":1,"queue":{"id":450,"mapId":12,"gameMode":"ARAM"}},"map":{"id":12,"gameMode":"ARAM"}}"#,
+        )
+        .unwrap();
+        assert_eq!(session.phase, GameflowPhase::ChampSelect);
+        assert_eq!(session.game_mode(), GameMode::Aram);
+
+        let lobby: Lobby = serde_json::from_str(
+            r#"{"gameConfig":{"queueId":1900,"mapId":11,"gameMode":"URF","isCustom":false}}"#,
+        )
+        .unwrap();
+        assert_eq!(lobby.game_mode(), GameMode::Urf);
+        assert_eq!(GameflowSession::default().game_mode(), GameMode::Classic);
+    }
+
+    #[test]
+    fn filter_sources_by_mode() {
+        let sources = vec![
+            source("op.gg", None, None),
+            source("op.gg-aram", Some(true), None),
+            source("murderbridge", Some(true), Some(false)),
+            source("op.gg-urf", Some(false), Some(true)),
+        ];
+        let values = |mode: GameMode| -> Vec<String> {
+            mode.filter_sources(&sources)
+                .iter()
+                .map(|s| s.value.clone())
+                .collect()
+        };
+
+        assert_eq!(values(GameMode::Classic), vec!["op.gg"]);
+        assert_eq!(values(GameMode::Aram), vec!["op.gg-aram", "murderbridge"]);
+        assert_eq!(values(GameMode::Urf), vec!["op.gg-urf"]);
+        assert_eq!(values(GameMode::Arena), vec!["op.gg"]);
+    }
+}
@@ -0,0 +1,318 @@
+use std::fmt;
+
+use futures::{channel::mpsc, Stream, StreamExt};
+use kv_log_macro::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::events::{ConnectionEvent, EventInput, EventType, LcuEvent, LcuEventClient};
+
+pub const GAMEFLOW_PHASE_URI: &str = "/lol-gameflow/v1/gameflow-phase";
+
+/// `/lol-gameflow/v1/gameflow-phase`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum GameflowPhase {
+    /// Also used while no client is connected.
+    #[default]
+    None,
+    Lobby,
+    Matchmaking,
+    CheckedIntoTournament,
+    ReadyCheck,
+    ChampSelect,
+    GameStart,
+    FailedToLaunch,
+    InProgress,
+    Reconnect,
+    WaitingForStats,
+    PreEndOfGame,
+    EndOfGame,
+    TerminatedInError,
+    Unknown(String),
+}
+
+impl GameflowPhase {
+    pub fn as_str(&self) -> &str {
+        match self {
+            GameflowPhase::None => "None",
+            GameflowPhase::Lobby => "Lobby",
+            GameflowPhase::Matchmaking => "Matchmaking",
+            GameflowPhase::CheckedIntoTournament => "CheckedIntoTournament",
+            GameflowPhase::ReadyCheck => "ReadyCheck",
+            GameflowPhase::ChampSelect => "ChampSelect",
+            GameflowPhase::GameStart => "GameStart",
+            GameflowPhase::FailedToLaunch => "FailedToLaunch",
+            GameflowPhase::InProgress => "InProgress",
+            GameflowPhase::Reconnect => "Reconnect",
+            GameflowPhase::WaitingForStats => "WaitingForStats",
+            GameflowPhase::PreEndOfGame => "PreEndOfGame",
+            GameflowPhase::EndOfGame => "EndOfGame",
+            GameflowPhase::TerminatedInError => "TerminatedInError",
+            GameflowPhase::Unknown(phase) => phase,
+        }
+    }
+
+    /// The game process is running or about to.
+    pub fn is_in_game(&self) -> bool {
+        matches!(
+            self,
+            GameflowPhase::GameStart | GameflowPhase::InProgress | GameflowPhase::Reconnect
+        )
+    }
+}
+
+impl From<&str> for GameflowPhase {
+    fn from(phase: &str) -> Self {
+        match phase {
+            "None" | "" => GameflowPhase::None,
+            "Lobby" => GameflowPhase::Lobby,
+            "Matchmaking" => GameflowPhase::Matchmaking,
+            "CheckedIntoTournament" => GameflowPhase::CheckedIntoTournament,
+            "ReadyCheck" => GameflowPhase::ReadyCheck,
+            "ChampSelect" => GameflowPhase::ChampSelect,
+            "GameStart" => GameflowPhase::GameStart,
+            "FailedToLaunch" => GameflowPhase::FailedToLaunch,
+            "InProgress" => GameflowPhase::InProgress,
+            "Reconnect" => GameflowPhase::Reconnect,
+            "WaitingForStats" => GameflowPhase::WaitingForStats,
+            "PreEndOfGame" => GameflowPhase::PreEndOfGame,
+            "EndOfGame" => GameflowPhase::EndOfGame,
+            "TerminatedInError" => GameflowPhase::TerminatedInError,
+            other => GameflowPhase::Unknown(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for GameflowPhase {
+    fn from(phase: String) -> Self {
+        GameflowPhase::from(phase.as_str())
+    }
+}
+
+impl From<GameflowPhase> for String {
+    fn from(phase: GameflowPhase) -> Self {
+        phase.as_str().to_string()
+    }
+}
+
+impl fmt::Display for GameflowPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhaseTransition {
+    pub from: GameflowPhase,
+    pub to: GameflowPhase,
+}
+
+impl PhaseTransition {
+    pub fn entered(&self, phase: &GameflowPhase) -> bool {
+        &self.to == phase && &self.from != phase
+    }
+
+    pub fn left(&self, phase: &GameflowPhase) -> bool {
+        &self.from == phase && &self.to != phase
+    }
+
+    pub fn is_game_started(&self) -> bool {
+        self.to.is_in_game() && !self.from.is_in_game()
+    }
+}
+
+/// Follows the gameflow phase of the client through websocket events.
+///
+/// The phase is read once on every (re)connect, so it is correct even if
+/// the client was started before the tracker. It drops back to `None` when
+/// the client disconnects.
+pub struct GameflowTracker {
+    phase: watch::Receiver<GameflowPhase>,
+    task: JoinHandle<()>,
+}
+
+impl GameflowTracker {
+    pub fn spawn(
+        events: &LcuEventClient,
+    ) -> (GameflowTracker, mpsc::UnboundedReceiver<PhaseTransition>) {
+        GameflowTracker::follow(events.inputs(GAMEFLOW_PHASE_URI))
+    }
+
+    /// Follows `inputs` instead of a live client, e.g. a replayed
+    /// [`Recording`](crate::recording::Recording).
+    pub fn follow<S>(mut inputs: S) -> (GameflowTracker, mpsc::UnboundedReceiver<PhaseTransition>)
+    where
+        S: Stream<Item = EventInput> + Unpin + Send + 'static,
+    {
+        let (phase_tx, phase) = watch::channel(GameflowPhase::None);
+        let (tx, rx) = mpsc::unbounded();
+
+        let task = tokio::spawn(async move {
+            while let Some(input) = inputs.next().await {
+                let next = match input {
+                    EventInput::Event(event) => phase_of(&event),
+                    EventInput::Connection(ConnectionEvent::Connected(client)) => {
+                        match client.get_gameflow_phase().await {
+                            Ok(phase) => phase,
+                            Err(e) => {
+                                warn!("[gameflow] {}", e);
+                                continue;
+                            }
+                        }
+                    }
+                    EventInput::Connection(ConnectionEvent::Disconnected) => GameflowPhase::None,
+                };
+
+                let from = phase_tx.send_replace(next.clone());
+                if from == next {
+                    continue;
+                }
+                let transition = PhaseTransition { from, to: next };
+                info!("[gameflow] {} -> {}", transition.from, transition.to);
+                // the current phase stays available without a listener
+                let _ = tx.unbounded_send(transition);
+            }
+        });
+
+        (GameflowTracker { phase, task }, rx)
+    }
+
+    pub fn current(&self) -> GameflowPhase {
+        self.phase.borrow().clone()
+    }
+
+    /// Waits for the next phase change, `None` once the tracker stopped.
+    pub async fn changed(&mut self) -> Option<GameflowPhase> {
+        self.phase.changed().await.ok()?;
+        Some(self.phase.borrow_and_update().clone())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+fn phase_of(event: &LcuEvent) -> GameflowPhase {
+    match (&event.event_type, event.data.as_str()) {
+        (EventType::Delete, _) | (_, None) => GameflowPhase::None,
+        (_, Some(phase)) => GameflowPhase::from(phase),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording::Recording;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    fn event(event_type: EventType, data: Value) -> EventInput {
+        EventInput::Event(LcuEvent {
+            uri: GAMEFLOW_PHASE_URI.to_string(),
+            event_type,
+            data,
+        })
+    }
+
+    async fn next(rx: &mut mpsc::UnboundedReceiver<PhaseTransition>) -> PhaseTransition {
+        tokio::time::timeout(Duration::from_secs(2), rx.next())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_phases() {
+        let phase: GameflowPhase = serde_json::from_str(r#""ChampSelect""#).unwrap();
+        assert_eq!(phase, GameflowPhase::ChampSelect);
+        assert_eq!(
+            GameflowPhase::from("Fancy"),
+            GameflowPhase::Unknown("Fancy".to_string())
+        );
+        assert_eq!(
+            serde_json::to_string(&GameflowPhase::EndOfGame).unwrap(),
+            r#""EndOfGame""#
+        );
+
+        let started = PhaseTransition {
+            from: GameflowPhase::ChampSelect,
+            to: GameflowPhase::GameStart,
+        };
+        assert!(started.is_game_started());
+        assert!(started.left(&GameflowPhase::ChampSelect));
+        assert!(!started.entered(&GameflowPhase::ChampSelect));
+
+        let reconnect = PhaseTransition {
+            from: GameflowPhase::InProgress,
+            to: GameflowPhase::Reconnect,
+        };
+        assert!(!reconnect.is_game_started());
+    }
+
+    #[tokio::test]
+    async fn track_phase_transitions() {
+        let (tx, inputs) = mpsc::unbounded();
+        let (mut tracker, mut transitions) = GameflowTracker::follow(inputs);
+        assert_eq!(tracker.current(), GameflowPhase::None);
+
+        tx.unbounded_send(event(EventType::Update, json!("Lobby")))
+            .unwrap();
+        tx.unbounded_send(event(EventType::Update, json!("Lobby")))
+            .unwrap();
+        tx.unbounded_send(event(EventType::Update, json!("ChampSelect")))
+            .unwrap();
+        tx.unbounded_send(EventInput::Connection(ConnectionEvent::Disconnected))
+            .unwrap();
+
+        assert_eq!(
+            next(&mut transitions).await,
+            PhaseTransition {
+                from: GameflowPhase::None,
+                to: GameflowPhase::Lobby
+            }
+        );
+        // the repeated `Lobby` is not a transition
+        let entered = next(&mut transitions).await;
+        assert!(entered.entered(&GameflowPhase::ChampSelect));
+        assert_eq!(entered.from, GameflowPhase::Lobby);
+        assert_eq!(
+            next(&mut transitions).await,
+            PhaseTransition {
+                from: GameflowPhase::ChampSelect,
+                to: GameflowPhase::None
+            }
+        );
+        assert_eq!(tracker.current(), GameflowPhase::None);
+
+        tx.unbounded_send(event(EventType::Update, json!("InProgress")))
+            .unwrap();
+        assert!(next(&mut transitions).await.is_game_started());
+        assert_eq!(tracker.changed().await, Some(GameflowPhase::InProgress));
+
+        drop(tx);
+        assert_eq!(tracker.changed().await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn replay_recorded_session() {
+        let recording =
+            Recording::parse(include_str!("../fixtures/recordings/gameflow.jsonl")).unwrap();
+        let replay = recording.replay(GAMEFLOW_PHASE_URI, 10.0).unwrap();
+        let (tracker, transitions) = GameflowTracker::follow(replay.map(EventInput::Event));
+
+        let phases = transitions.map(|t| t.to).collect::<Vec<_>>().await;
+        assert_eq!(
+            phases,
+            vec![
+                GameflowPhase::Lobby,
+                GameflowPhase::Matchmaking,
+                GameflowPhase::ReadyCheck,
+                GameflowPhase::ChampSelect,
+                GameflowPhase::GameStart,
+                GameflowPhase::InProgress,
+            ]
+        );
+        assert_eq!(tracker.current(), GameflowPhase::InProgress);
+    }
+}
@@ -0,0 +1,205 @@
+use std::path::{Path, PathBuf};
+
+use kv_log_macro::info;
+
+use crate::{discovery_error::DiscoveryError, region::Region};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallLayout {
+    /// `<root>/Config/Champions`
+    Riot,
+    /// `<root>/Game/Config/Champions`, the client lives in `<root>/LeagueClient`
+    Tencent,
+}
+
+impl InstallLayout {
+    fn config_dir(&self, root: &Path) -> PathBuf {
+        match self {
+            InstallLayout::Riot => root.join("Config"),
+            InstallLayout::Tencent => root.join("Game").join("Config"),
+        }
+    }
+
+    /// The folder next to the config one that tells the layouts apart, the
+    /// client's own `LeagueClient/Config` has neither.
+    fn marker_dir(&self, root: &Path) -> PathBuf {
+        match self {
+            InstallLayout::Riot => root.join("Game"),
+            InstallLayout::Tencent => root.join("LeagueClient"),
+        }
+    }
+
+    fn matches(&self, root: &Path) -> bool {
+        self.config_dir(root).is_dir() && self.marker_dir(root).is_dir()
+    }
+}
+
+/// A verified game install directory.
+///
+/// The `Config` folder and the layout's game or client folder have to exist,
+/// `Champions` is created on demand when builds are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallDir {
+    root: PathBuf,
+    layout: InstallLayout,
+}
+
+impl InstallDir {
+    /// Canonicalizes `dir` and looks for a known layout in it or its parent,
+    /// as `--install-directory` points to the client folder on Tencent.
+    /// The layout matching `region` is tried at both places first.
+    pub fn resolve(dir: &str, region: &Region) -> Result<InstallDir, DiscoveryError> {
+        let unreadable = || DiscoveryError::UnreadableInstallDir(dir.to_string());
+        if dir.is_empty() {
+            return Err(unreadable());
+        }
+
+        let path = Path::new(dir).canonicalize().map_err(|_| unreadable())?;
+        let layouts = if region.is_tencent() {
+            [InstallLayout::Tencent, InstallLayout::Riot]
+        } else {
+            [InstallLayout::Riot, InstallLayout::Tencent]
+        };
+
+        for layout in layouts {
+            for root in std::iter::once(path.as_path()).chain(path.parent()) {
+                if layout.matches(root) {
+                    return Ok(InstallDir {
+                        root: root.to_path_buf(),
+                        layout,
+                    });
+                }
+            }
+        }
+
+        Err(unreadable())
+    }
+
+    /// Uses the detected directory if it is valid, otherwise falls back to
+    /// the one configured by the user.
+    pub fn resolve_or_override(
+        detected: &str,
+        region: &Region,
+        manual: Option<&str>,
+    ) -> Result<InstallDir, DiscoveryError> {
+        match (InstallDir::resolve(detected, region), manual) {
+            (Ok(dir), _) => Ok(dir),
+            (Err(e), Some(manual)) => {
+                info!("[install_dir] {}, using {}", e, manual);
+                InstallDir::resolve(manual, region)
+            }
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn layout(&self) -> InstallLayout {
+        self.layout
+    }
+
+    pub fn champions_folder(&self) -> String {
+        self.layout
+            .config_dir(&self.root)
+            .join("Champions")
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("champr_{name}_{}", nanoid::nanoid!(6)));
+        fs::create_dir_all(&root).unwrap();
+        root.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn resolve_riot_layout() {
+        let root = temp_root("riot");
+        fs::create_dir_all(root.join("Config")).unwrap();
+        fs::create_dir_all(root.join("Game")).unwrap();
+
+        let dir = InstallDir::resolve(&format!("{}/", root.display()), &Region::NA).unwrap();
+        assert_eq!(dir.root(), root.as_path());
+        assert_eq!(dir.layout(), InstallLayout::Riot);
+        assert_eq!(
+            dir.champions_folder(),
+            root.join("Config/Champions").to_string_lossy()
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn resolve_tencent_layout_from_client_dir() {
+        let root = temp_root("tencent");
+        fs::create_dir_all(root.join("Game/Config")).unwrap();
+        fs::create_dir_all(root.join("LeagueClient")).unwrap();
+
+        let client_dir = root.join("LeagueClient");
+        let dir = InstallDir::resolve(client_dir.to_str().unwrap(), &Region::Tencent).unwrap();
+        assert_eq!(dir.root(), root.as_path());
+        assert_eq!(dir.layout(), InstallLayout::Tencent);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn prefer_game_config_over_client_config() {
+        let root = temp_root("tencent_client_config");
+        fs::create_dir_all(root.join("Game/Config/Champions")).unwrap();
+        fs::create_dir_all(root.join("LeagueClient/Config")).unwrap();
+
+        let client_dir = root.join("LeagueClient");
+        for region in [Region::Tencent, Region::NA] {
+            let dir = InstallDir::resolve(client_dir.to_str().unwrap(), &region).unwrap();
+            assert_eq!(dir.root(), root.as_path());
+            assert_eq!(dir.layout(), InstallLayout::Tencent);
+            assert_eq!(
+                dir.champions_folder(),
+                root.join("Game/Config/Champions").to_string_lossy()
+            );
+        }
+
+        // a config folder alone is not an install
+        let lone = temp_root("lone_config");
+        fs::create_dir_all(lone.join("Config")).unwrap();
+        assert!(InstallDir::resolve(lone.to_str().unwrap(), &Region::NA).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&lone).unwrap();
+    }
+
+    #[test]
+    fn reject_unknown_layout() {
+        let root = temp_root("empty");
+        let missing = root.join("missing");
+
+        assert_eq!(
+            InstallDir::resolve(missing.to_str().unwrap(), &Region::NA),
+            Err(DiscoveryError::UnreadableInstallDir(
+                missing.to_str().unwrap().to_string()
+            ))
+        );
+        assert!(InstallDir::resolve("", &Region::NA).is_err());
+
+        let manual = temp_root("manual");
+        fs::create_dir_all(manual.join("Config")).unwrap();
+        fs::create_dir_all(manual.join("Game")).unwrap();
+        let dir = InstallDir::resolve_or_override(
+            missing.to_str().unwrap(),
+            &Region::NA,
+            manual.to_str(),
+        )
+        .unwrap();
+        assert_eq!(dir.root(), manual.as_path());
+
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&manual).unwrap();
+    }
+}
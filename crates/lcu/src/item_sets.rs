@@ -0,0 +1,153 @@
+use nanoid::nanoid;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::builds::ItemBuild;
+
+const TAG_PREFIX: &str = "champr";
+
+/// `/lol-item-sets/v1/item-sets/{summonerId}/sets`. Sets are kept as raw
+/// JSON so fields we do not model survive a round trip.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ItemSets {
+    pub account_id: i64,
+    pub item_sets: Vec<Value>,
+    pub timestamp: i64,
+}
+
+/// Marks an item set as uploaded by ChampR. It lives in the `uid` of the set
+/// as `champr:<source>:<champion>:<random>`, which the client keeps as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemSetTag {
+    pub source: String,
+    pub champion: String,
+}
+
+impl ItemSetTag {
+    pub fn new(source: &str, champion: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            champion: champion.to_string(),
+        }
+    }
+
+    pub fn parse(uid: &str) -> Option<ItemSetTag> {
+        let mut parts = uid.splitn(4, ':');
+        if parts.next()? != TAG_PREFIX {
+            return None;
+        }
+        let source = parts.next()?;
+        let champion = parts.next()?;
+        parts.next()?;
+        Some(ItemSetTag::new(source, champion))
+    }
+
+    pub fn of(set: &Value) -> Option<ItemSetTag> {
+        set.get("uid")?.as_str().and_then(ItemSetTag::parse)
+    }
+
+    fn uid(&self) -> String {
+        format!(
+            "{TAG_PREFIX}:{}:{}:{}",
+            self.source,
+            self.champion,
+            nanoid!(8)
+        )
+    }
+}
+
+pub fn to_item_set(build: &ItemBuild, tag: &ItemSetTag) -> Value {
+    let mut set = serde_json::to_value(build).unwrap_or_default();
+    if let Value::Object(map) = &mut set {
+        map.insert("uid".to_string(), Value::String(tag.uid()));
+        // the client rejects `null` here
+        if build.preferred_item_slots.is_none() {
+            map.insert("preferredItemSlots".to_string(), Value::Array(vec![]));
+        }
+    }
+    set
+}
+
+impl ItemSets {
+    /// Replaces the sets tagged with `tag` by `builds`, sets of the user and
+    /// of other sources or champions are kept.
+    pub fn merge(&mut self, tag: &ItemSetTag, builds: &[ItemBuild]) {
+        self.remove_tagged(|t| t == tag);
+        self.item_sets
+            .extend(builds.iter().map(|b| to_item_set(b, tag)));
+    }
+
+    /// Removes ChampR sets whose tag matches `filter`, returns how many were
+    /// removed.
+    pub fn remove_tagged<F>(&mut self, filter: F) -> usize
+    where
+        F: Fn(&ItemSetTag) -> bool,
+    {
+        let before = self.item_sets.len();
+        self.item_sets
+            .retain(|set| !ItemSetTag::of(set).is_some_and(|tag| filter(&tag)));
+        before - self.item_sets.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn build(title: &str) -> ItemBuild {
+        ItemBuild {
+            title: title.to_string(),
+            associated_champions: vec![103],
+            ..Default::default()
+        }
+    }
+
+    fn titles(sets: &ItemSets) -> Vec<&str> {
+        sets.item_sets
+            .iter()
+            .map(|s| s["title"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn tag_item_sets() {
+        let tag = ItemSetTag::new("op.gg", "Ahri");
+        let set = to_item_set(&build("Ahri mid"), &tag);
+        assert_eq!(set["title"], "Ahri mid");
+        assert_eq!(set["associatedChampions"], json!([103]));
+        assert_eq!(set["preferredItemSlots"], json!([]));
+        assert_eq!(ItemSetTag::of(&set), Some(tag));
+
+        assert_eq!(ItemSetTag::parse("a1b2c3"), None);
+        assert_eq!(ItemSetTag::parse("champr:op.gg"), None);
+        assert_eq!(ItemSetTag::of(&json!({ "title": "mine" })), None);
+    }
+
+    #[test]
+    fn merge_keeps_user_sets() {
+        let mut sets: ItemSets = serde_json::from_value(json!({
+            "accountId": 1,
+            "timestamp": 2,
+            "itemSets": [
+                { "uid": "c0ffee", "title": "my set", "blocks": [], "hideIfSummonerSpell": "" }
+            ]
+        }))
+        .unwrap();
+
+        let ahri = ItemSetTag::new("op.gg", "Ahri");
+        let lux = ItemSetTag::new("op.gg", "Lux");
+        sets.merge(&ahri, &[build("Ahri 1"), build("Ahri 2")]);
+        sets.merge(&lux, &[build("Lux")]);
+        assert_eq!(titles(&sets), vec!["my set", "Ahri 1", "Ahri 2", "Lux"]);
+
+        // applying again replaces only the sets of the same source and champion
+        sets.merge(&ahri, &[build("Ahri 3")]);
+        assert_eq!(titles(&sets), vec!["my set", "Lux", "Ahri 3"]);
+        assert_eq!(sets.item_sets[0]["hideIfSummonerSpell"], "");
+
+        assert_eq!(sets.remove_tagged(|_| true), 2);
+        assert_eq!(titles(&sets), vec!["my set"]);
+    }
+}
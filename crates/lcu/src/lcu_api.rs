@@ -1,129 +1,552 @@
-use std::{collections::HashMap, time::Duration};
+use base64::{engine::general_purpose, Engine as _};
 use bytes::Bytes;
+use kv_log_macro::{info, warn};
 use lazy_static::lazy_static;
-use reqwest::{Client, Url};
+use reqwest::{header::HeaderValue, Method, RequestBuilder, Response, Url};
+use reqwest_websocket::{RequestBuilderExt, WebSocket};
 use serde::de::DeserializeOwned;
 use serde_derive::{Deserialize, Serialize};
 use serde_json::Value;
-use reqwest_websocket::{RequestBuilderExt, WebSocket, Message};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use crate::{
-    builds::{ItemBuild, Rune},
+    asset_cache::{champion_icon_path, rune_image_path, AssetCache},
+    builds::{BuildSection, ItemBuild, Rune},
+    champ_select::ChampSelectSession,
+    cmd::CommandLineOutput,
+    game_mode::{GameMode, GameflowSession, Lobby},
+    gameflow::{GameflowPhase, GAMEFLOW_PHASE_URI},
+    item_sets::{ItemSetTag, ItemSets},
     lcu_error::LcuError,
+    ready_check::{ReadyCheck, READY_CHECK_URI},
+    rune_validator::{RuneCheck, RuneValidator},
+    spells::{resolve_spells, FlashPreference},
     web::FetchError,
 };
 
+const LCU_USERNAME: &str = "riot";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
 lazy_static! {
     static ref CLIENT: reqwest::Client = {
         reqwest::Client::builder()
             .use_rustls_tls()
             .danger_accept_invalid_certs(true)
-            .timeout(Duration::from_secs(2))
+            .no_proxy()
+            .build()
+            .unwrap()
+    };
+    /// Websocket upgrades only work over HTTP/1.1, while the LCU would
+    /// negotiate HTTP/2 through ALPN.
+    static ref WS_CLIENT: reqwest::Client = {
+        reqwest::Client::builder()
+            .use_rustls_tls()
+            .danger_accept_invalid_certs(true)
+            .http1_only()
             .no_proxy()
             .build()
             .unwrap()
     };
 }
 
-pub fn make_client() -> &'static reqwest::Client {
-    &CLIENT
+/// Connection to one running League client.
+///
+/// The LCU serves a self-signed certificate on `127.0.0.1` and expects Basic
+/// auth with the `riot` user and the `--remoting-auth-token`.
+#[derive(Debug, Clone)]
+pub struct LcuClient {
+    port: String,
+    token: String,
+    base_url: Url,
+    auth_header: HeaderValue,
+    http: reqwest::Client,
+    ws_http: reqwest::Client,
+    timeout: Duration,
+    assets: Option<Arc<AssetCache>>,
 }
 
-pub async fn make_get_request<T: DeserializeOwned>(endpoint: &String) -> Result<T, LcuError> {
-    let client = make_client();
-    client
-        .get(endpoint)
-        .version(reqwest::Version::HTTP_2)
-        .header(reqwest::header::ACCEPT, "application/json")
-        .send()
-        .await
-        .map_err(LcuError::from)?
-        .json()
-        .await
-        .map_err(LcuError::from)
-}
+impl LcuClient {
+    pub fn new(port: &str, token: &str) -> Result<Self, LcuError> {
+        let base_url = format!("https://127.0.0.1:{port}")
+            .parse::<Url>()
+            .map_err(|e| LcuError::APIError(format!("invalid port {port:?}: {e}")))?;
+        let credentials = general_purpose::STANDARD.encode(format!("{LCU_USERNAME}:{token}"));
+        let mut auth_header = HeaderValue::from_str(&format!("Basic {credentials}"))
+            .map_err(|e| LcuError::APIError(e.to_string()))?;
+        auth_header.set_sensitive(true);
 
-pub async fn get_session(auth_url: &String) -> Result<Option<i64>, LcuError> {
-    let endpoint = format!("{auth_url}/lol-champ-select/v1/session");
-    let resp: Value = make_get_request(&endpoint).await?;
-
-    if let Some(cell_id) = resp["localPlayerCellId"].as_i64() {
-        let my_team = resp["myTeam"].as_array().unwrap();
-        for i in my_team {
-            let id = i["cellId"].as_i64().unwrap();
-            if id == cell_id {
-                let champ_id = i.get("championId").unwrap().as_i64().unwrap();
-                return Ok(Some(champ_id));
-            }
+        Ok(Self {
+            port: port.to_string(),
+            token: token.to_string(),
+            base_url,
+            auth_header,
+            http: CLIENT.clone(),
+            ws_http: WS_CLIENT.clone(),
+            timeout: DEFAULT_TIMEOUT,
+            assets: None,
+        })
+    }
+
+    pub fn from_cmd_output(output: &CommandLineOutput) -> Result<Self, LcuError> {
+        Self::new(&output.port, &output.token)
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Client for the REST requests. The websocket upgrade needs HTTP/1.1 and
+    /// goes through its own client, see [`Self::with_websocket_client`].
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Client for [`Self::connect_websocket`], it must not negotiate HTTP/2.
+    pub fn with_websocket_client(mut self, http: reqwest::Client) -> Self {
+        self.ws_http = http;
+        self
+    }
+
+    /// Serves images through `assets`, so each is downloaded once per game
+    /// version.
+    pub fn with_asset_cache(mut self, assets: Arc<AssetCache>) -> Self {
+        self.assets = Some(assets);
+        self
+    }
+
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    pub fn auth_header(&self) -> &HeaderValue {
+        &self.auth_header
+    }
+
+    pub fn http_client(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// `path` may carry a query string, e.g. `/lol-summoner/v2/summoners?ids=[1]`.
+    pub fn url(&self, path: &str) -> Url {
+        let (path, query) = match path.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path, None),
+        };
+        let mut url = self.base_url.clone();
+        url.set_path(path);
+        url.set_query(query);
+        url
+    }
+
+    /// Builds an authenticated request against `path`, e.g. `/lol-perks/v1/pages`.
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, self.url(path))
+            .version(reqwest::Version::HTTP_2)
+            .timeout(self.timeout)
+            .header(reqwest::header::AUTHORIZATION, self.auth_header.clone())
+            .header(reqwest::header::ACCEPT, "application/json")
+    }
+
+    /// Sends `req` and turns a non-success status into an [`LcuError`].
+    pub async fn send(&self, req: RequestBuilder) -> Result<Response, LcuError> {
+        let resp = req.send().await?;
+        let status = resp.status();
+        if status.is_success() {
+            return Ok(resp);
         }
 
-        let actions = resp["actions"].as_array().unwrap();
-        for row in actions {
-            for i in row.as_array().unwrap() {
-                let id = i["actorCellId"].as_i64().unwrap();
-                if id == cell_id && i["type"].as_str().unwrap() != "ban" {
-                    let champ_id = i.get("championId").unwrap().as_i64().unwrap();
-                    return Ok(Some(champ_id));
-                }
-            }
+        let path = resp.url().path().to_string();
+        let body = resp.bytes().await.unwrap_or_default();
+        Err(LcuError::from_status(status.as_u16(), &path, &body))
+    }
+
+    /// Sends `req` and decodes the body, keeping the JSON path of a mismatch.
+    pub async fn send_json<T: DeserializeOwned>(&self, req: RequestBuilder) -> Result<T, LcuError> {
+        let body = self.send(req).await?.bytes().await?;
+        let mut de = serde_json::Deserializer::from_slice(&body);
+        Ok(serde_path_to_error::deserialize(&mut de)?)
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, LcuError> {
+        self.send_json(self.request(Method::GET, path)).await
+    }
+
+    pub async fn get_champ_select_session(&self) -> Result<ChampSelectSession, LcuError> {
+        self.get("/lol-champ-select/v1/session").await
+    }
+
+    /// The champion picked or hovered by the local player, if any.
+    pub async fn get_session(&self) -> Result<Option<i64>, LcuError> {
+        Ok(self.get_champ_select_session().await?.my_champion())
+    }
+
+    pub async fn get_gameflow_phase(&self) -> Result<GameflowPhase, LcuError> {
+        self.get(GAMEFLOW_PHASE_URI).await
+    }
+
+    pub async fn get_gameflow_session(&self) -> Result<GameflowSession, LcuError> {
+        self.get("/lol-gameflow/v1/session").await
+    }
+
+    pub async fn get_lobby(&self) -> Result<Lobby, LcuError> {
+        self.get("/lol-lobby/v2/lobby").await
+    }
+
+    /// Mode of the current game, or of the lobby before the game is found.
+    pub async fn get_game_mode(&self) -> Result<GameMode, LcuError> {
+        let session = self.get_gameflow_session().await?;
+        if session.game_data.queue.id != 0 || !session.map.game_mode.is_empty() {
+            return Ok(session.game_mode());
         }
-    };
+        Ok(self.get_lobby().await?.game_mode())
+    }
 
-    Ok(None)
-}
+    pub async fn get_ready_check(&self) -> Result<ReadyCheck, LcuError> {
+        self.get(READY_CHECK_URI).await
+    }
+
+    pub async fn accept_ready_check(&self) -> Result<(), LcuError> {
+        let req = self.request(Method::POST, &format!("{READY_CHECK_URI}/accept"));
+        self.send(req).await?;
+        Ok(())
+    }
+
+    pub async fn decline_ready_check(&self) -> Result<(), LcuError> {
+        let req = self.request(Method::POST, &format!("{READY_CHECK_URI}/decline"));
+        self.send(req).await?;
+        Ok(())
+    }
+
+    pub async fn list_rune_pages(&self) -> Result<Vec<RunePage>, LcuError> {
+        self.get("/lol-perks/v1/pages").await
+    }
+
+    pub async fn get_current_rune_page(&self) -> Result<RunePage, LcuError> {
+        self.get("/lol-perks/v1/currentpage").await
+    }
+
+    pub async fn get_perk_inventory(&self) -> Result<PerkInventory, LcuError> {
+        self.get("/lol-perks/v1/inventory").await
+    }
+
+    pub async fn create_rune_page(&self, page: &RunePageInput) -> Result<RunePage, LcuError> {
+        let req = self.request(Method::POST, "/lol-perks/v1/pages").json(page);
+        self.send_json(req).await
+    }
 
-pub async fn apply_rune(endpoint: String, rune: Rune) -> Result<(), LcuError> {
-    let runes: Value = make_get_request(&format!("{endpoint}/lol-perks/v1/pages")).await?;
+    pub async fn update_rune_page(&self, id: i64, page: &RunePageInput) -> Result<(), LcuError> {
+        let req = self
+            .request(Method::PUT, &format!("/lol-perks/v1/pages/{id}"))
+            .json(page);
+        self.send(req).await?;
+        Ok(())
+    }
+
+    pub async fn delete_rune_page(&self, id: i64) -> Result<(), LcuError> {
+        let req = self.request(Method::DELETE, &format!("/lol-perks/v1/pages/{id}"));
+        self.send(req).await?;
+        Ok(())
+    }
+
+    pub async fn set_current_rune_page(&self, id: i64) -> Result<(), LcuError> {
+        let req = self
+            .request(Method::PUT, "/lol-perks/v1/currentpage")
+            .json(&id);
+        self.send(req).await?;
+        Ok(())
+    }
+
+    /// Writes `rune` into a page chosen by `strategy` and makes it current.
+    pub async fn apply_rune(
+        &self,
+        rune: &Rune,
+        strategy: &RuneApplyStrategy,
+    ) -> Result<(), LcuError> {
+        let pages = self.list_rune_pages().await?;
+        let inventory = self.get_perk_inventory().await?;
+        let page = RunePageInput::from_rune(rune, strategy.page_prefix());
 
-    let mut id = 0;
-    for r in runes.as_array().unwrap() {
-        if r["current"].as_bool().unwrap() {
-            id = r["id"].as_i64().unwrap();
-            break;
+        let id = match plan_rune_apply(&pages, &inventory, strategy)? {
+            RuneApplyPlan::Update(id) => {
+                self.update_rune_page(id, &page).await?;
+                id
+            }
+            RuneApplyPlan::Create => self.create_rune_page(&page).await?.id,
+            RuneApplyPlan::Replace(id) => {
+                self.delete_rune_page(id).await?;
+                self.create_rune_page(&page).await?.id
+            }
+        };
+        self.set_current_rune_page(id).await
+    }
+
+    pub async fn get_item_sets(&self, summoner_id: i64) -> Result<ItemSets, LcuError> {
+        self.get(&format!("/lol-item-sets/v1/item-sets/{summoner_id}/sets"))
+            .await
+    }
+
+    /// Replaces all item sets of the summoner, the LCU has no per-set endpoint.
+    pub async fn put_item_sets(&self, summoner_id: i64, sets: &ItemSets) -> Result<(), LcuError> {
+        let req = self
+            .request(
+                Method::PUT,
+                &format!("/lol-item-sets/v1/item-sets/{summoner_id}/sets"),
+            )
+            .json(sets);
+        self.send(req).await?;
+        Ok(())
+    }
+
+    async fn update_item_sets<F, T>(&self, f: F) -> Result<T, LcuError>
+    where
+        F: FnOnce(&mut ItemSets) -> T,
+    {
+        let summoner = self.get_current_summoner().await?;
+        let mut sets = self.get_item_sets(summoner.summoner_id).await?;
+        let result = f(&mut sets);
+        self.put_item_sets(summoner.summoner_id, &sets).await?;
+        Ok(result)
+    }
+
+    /// Uploads `builds` as item sets of the current summoner, replacing the
+    /// ones uploaded before for the same source and champion.
+    pub async fn upload_item_sets(
+        &self,
+        source: &str,
+        champion_alias: &str,
+        builds: &[ItemBuild],
+    ) -> Result<(), LcuError> {
+        let tag = ItemSetTag::new(source, champion_alias);
+        self.update_item_sets(|sets| sets.merge(&tag, builds)).await
+    }
+
+    /// Removes every item set uploaded by ChampR, returns how many were removed.
+    pub async fn remove_champr_item_sets(&self) -> Result<usize, LcuError> {
+        self.update_item_sets(|sets| sets.remove_tagged(|_| true))
+            .await
+    }
+
+    pub async fn set_summoner_spells(
+        &self,
+        spell1_id: u64,
+        spell2_id: u64,
+    ) -> Result<(), LcuError> {
+        let req = self
+            .request(Method::PATCH, "/lol-champ-select/v1/session/my-selection")
+            .json(&serde_json::json!({
+                "spell1Id": spell1_id,
+                "spell2Id": spell2_id,
+            }));
+        self.send(req).await?;
+        Ok(())
+    }
+
+    /// Sets the spells of `section` for the current champ select.
+    pub async fn apply_spells(
+        &self,
+        section: &BuildSection,
+        flash: FlashPreference,
+    ) -> Result<(), LcuError> {
+        let spells = section.spells.as_deref().unwrap_or_default();
+        let (spell1, spell2) = resolve_spells(spells, flash)
+            .ok_or_else(|| LcuError::APIError(format!("invalid summoner spells {spells:?}")))?;
+        self.set_summoner_spells(spell1, spell2).await
+    }
+
+    /// Applies `rune`, the item builds and the spells of `section`. Every
+    /// step runs even if a previous one failed.
+    pub async fn appy_rune_and_builds(
+        &self,
+        rune: &Rune,
+        section: &BuildSection,
+        source: &str,
+        options: &ApplyOptions,
+    ) -> ApplyReport {
+        let rune = match self.check_rune(rune, options.rune_check).await {
+            Ok(rune) => self.apply_rune(&rune, &options.rune_strategy).await.into(),
+            Err(e) => StepOutcome::Failed(e),
+        };
+
+        let item_sets = if section.item_builds.is_empty() {
+            StepOutcome::Skipped
+        } else {
+            self.upload_item_sets(source, &section.alias, &section.item_builds)
+                .await
+                .into()
+        };
+
+        let spells = if section.spells.is_some() {
+            self.apply_spells(section, options.flash).await.into()
+        } else {
+            StepOutcome::Skipped
+        };
+
+        let report = ApplyReport {
+            rune,
+            item_sets,
+            spells,
+        };
+        info!("[lcu_api] apply {}: {:?}", section.alias, &report);
+        report
+    }
+
+    /// Validates or repairs `rune` against the client's styles. Pages are
+    /// applied unchecked if the styles cannot be read.
+    async fn check_rune(&self, rune: &Rune, check: RuneCheck) -> Result<Rune, LcuError> {
+        if check == RuneCheck::Off {
+            return Ok(rune.clone());
         }
-        if r["isDeletable"].as_bool().unwrap() {
-            id = r["id"].as_i64().unwrap();
+        let validator = match RuneValidator::fetch(self).await {
+            Ok(validator) => validator,
+            Err(e) => {
+                warn!("[lcu_api] cannot validate rune page: {}", e);
+                return Ok(rune.clone());
+            }
+        };
+
+        let result = match check {
+            RuneCheck::Repair => validator.repair(rune).inspect(|repaired| {
+                if repaired != rune {
+                    info!(
+                        "[lcu_api] repaired rune page {}: {:?}",
+                        rune.name, repaired.selected_perk_ids
+                    );
+                }
+            }),
+            _ => match validator.validate(rune) {
+                issues if issues.is_empty() => Ok(rune.clone()),
+                issues => Err(issues),
+            },
+        };
+        result.map_err(|issues| {
+            let issues: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+            LcuError::InvalidRunePage(issues.join(", "))
+        })
+    }
+
+    pub async fn get_current_summoner(&self) -> Result<Summoner, LcuError> {
+        self.get("/lol-summoner/v1/current-summoner").await
+    }
+
+    pub async fn list_available_champions(
+        &self,
+        summoner_id: i64,
+    ) -> Result<Vec<SummonerChampion>, LcuError> {
+        self.get(&format!(
+            "/lol-champions/v1/inventories/{summoner_id}/champions"
+        ))
+        .await
+    }
+
+    /// Ids of the champions the current summoner can play, see
+    /// [`SummonerChampion::is_playable`].
+    pub async fn list_playable_champion_ids(&self) -> Result<HashSet<i64>, LcuError> {
+        let summoner = self.get_current_summoner().await?;
+        let champions = self.list_available_champions(summoner.summoner_id).await?;
+        Ok(champions
+            .iter()
+            .filter(|c| c.is_playable())
+            .map(|c| c.id)
+            .collect())
+    }
+
+    /// Hovers `champion_id` in a champ select action, `lock` completes it.
+    pub async fn update_action(
+        &self,
+        action_id: i64,
+        champion_id: i64,
+        lock: bool,
+    ) -> Result<(), LcuError> {
+        let mut body = serde_json::json!({ "championId": champion_id });
+        if lock {
+            body["completed"] = Value::Bool(true);
         }
+        let req = self
+            .request(
+                Method::PATCH,
+                &format!("/lol-champ-select/v1/session/actions/{action_id}"),
+            )
+            .json(&body);
+        self.send(req).await?;
+        Ok(())
     }
 
-    let client = make_client();
-    if id > 0 {
-        let _ = client
-            .delete(format!("{endpoint}/lol-perks/v1/pages/{id}"))
-            .version(reqwest::Version::HTTP_2)
-            .header(reqwest::header::ACCEPT, "application/json")
-            .send()
-            .await?;
+    /// e.g. `14.20.625.6589`
+    pub async fn get_game_version(&self) -> Result<String, LcuError> {
+        self.get("/lol-patch/v1/game-version").await
     }
 
-    let _ = client
-        .post(format!("{endpoint}/lol-perks/v1/pages"))
-        .version(reqwest::Version::HTTP_2)
-        .header(reqwest::header::ACCEPT, "application/json")
-        .json(&rune)
-        .send()
-        .await?;
-    Ok(())
-}
+    pub async fn list_all_perks(&self) -> Result<Vec<Perk>, LcuError> {
+        self.get("/lol-perks/v1/perks").await
+    }
 
-pub async fn appy_rune_and_builds(
-    _endpoint: String,
-    _rune: Rune,
-    _builds: Vec<ItemBuild>,
-) -> Result<(), LcuError> {
-    Ok(())
-}
+    pub async fn list_all_styles(&self) -> Result<Vec<RuneStyle>, LcuError> {
+        self.get("/lol-perks/v1/styles").await
+    }
 
-pub async fn get_rune_image(endpoint: String, icon_path: String) -> Result<Bytes, FetchError> {
-    let client = make_client();
-    let url = format!("{endpoint}/lol-game-data/assets/v1/{icon_path}");
-    if let Ok(resp) = client.get(&url).send().await {
-        if let Ok(bytes) = resp.bytes().await {
-            return Ok(bytes);
+    /// Downloads an asset by its absolute LCU path, such as the `iconPath`
+    /// of a perk.
+    pub async fn fetch_asset(&self, path: &str) -> Result<Bytes, FetchError> {
+        match self.request(Method::GET, path).send().await {
+            Ok(res) => {
+                if res.status().is_success() {
+                    return res.bytes().await.map_err(|_| FetchError::Failed);
+                }
+                Err(FetchError::Failed)
+            }
+            Err(err) => {
+                warn!("[lcu_api] error fetching asset {}: {}", path, err);
+                Err(FetchError::Failed)
+            }
         }
     }
 
-    Err(FetchError::Failed)
+    /// `fetch_asset` behind the asset cache, if there is one.
+    async fn get_asset(&self, path: &str) -> Result<Bytes, FetchError> {
+        let Some(assets) = &self.assets else {
+            return self.fetch_asset(path).await;
+        };
+        assets.get(Some(self), path).await.map_err(|e| {
+            warn!("[lcu_api] {}", e);
+            FetchError::Failed
+        })
+    }
+
+    pub async fn get_rune_image(&self, icon_path: &str) -> Result<Bytes, FetchError> {
+        self.get_asset(&rune_image_path(icon_path)).await
+    }
+
+    pub async fn get_champion_icon_by_id(&self, id: i64) -> Result<Bytes, FetchError> {
+        self.get_asset(&champion_icon_path(id)).await
+    }
+
+    pub async fn connect_websocket(&self) -> Result<WebSocket, reqwest_websocket::Error> {
+        let mut url = self.base_url.clone();
+        let _ = url.set_scheme("wss");
+        let response = self
+            .ws_http
+            .get(url)
+            .header(reqwest::header::AUTHORIZATION, self.auth_header.clone())
+            .upgrade()
+            .send()
+            .await?;
+        response.into_websocket().await
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -157,11 +580,6 @@ pub struct RerollPoints {
     pub points_to_reroll: i64,
 }
 
-pub async fn get_current_summoner(endpoint: &String) -> Result<Summoner, LcuError> {
-    let url = format!("{endpoint}/lol-summoner/v1/current-summoner");
-    make_get_request(&url).await
-}
-
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SummonerChampion {
@@ -189,6 +607,13 @@ pub struct SummonerChampion {
     pub title: String,
 }
 
+impl SummonerChampion {
+    /// Owned, rented or in the free rotation.
+    pub fn is_playable(&self) -> bool {
+        self.ownership.owned || self.ownership.rental.rented || self.free_to_play
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Ownership {
@@ -223,14 +648,6 @@ pub struct TacticalInfo {
     pub style: i64,
 }
 
-pub async fn list_available_champions(
-    endpoint: &String,
-    summoner_id: i64,
-) -> Result<Vec<SummonerChampion>, LcuError> {
-    let url = format!("{endpoint}/lol-champions/v1/inventories/{summoner_id}/champions");
-    make_get_request(&url).await
-}
-
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Perk {
@@ -246,32 +663,6 @@ pub struct Perk {
     pub tooltip: String,
 }
 
-pub async fn list_all_perks(endpoint: &String) -> Result<Vec<Perk>, LcuError> {
-    let url = format!("{endpoint}/lol-perks/v1/perks");
-    make_get_request(&url).await
-}
-
-pub async fn fetch_image_data(url: &String) -> Result<Bytes, FetchError> {
-    let client = make_client();
-    match client.get(url).send().await.map_err(|_| FetchError::Failed) {
-        Ok(res) => {
-            if res.status().is_success() {
-                return res.bytes().await.map_err(|_| FetchError::Failed);
-            }
-            Err(FetchError::Failed)
-        }
-        Err(err) => {
-            println!("Error fetching rune image: {:?}", err);
-            Err(FetchError::Failed)
-        }
-    }
-}
-
-pub async fn fetch_rune_image(endpoint: &String, icon_path: &String) -> Result<Bytes, FetchError> {
-    let url = format!("{endpoint}{icon_path}");
-    fetch_image_data(&url).await
-}
-
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RuneStyle {
@@ -305,28 +696,458 @@ pub struct SubStyleBonu {
     pub style_id: i64,
 }
 
-pub async fn list_all_styles(endpoint: &String) -> Result<Vec<RuneStyle>, LcuError> {
-    let url = format!("{endpoint}/lol-perks/v1/styles");
-    make_get_request(&url).await
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RunePage {
+    pub current: bool,
+    pub id: i64,
+    pub is_active: bool,
+    pub is_deletable: bool,
+    pub is_editable: bool,
+    pub is_valid: bool,
+    pub last_modified: i64,
+    pub name: String,
+    pub order: i64,
+    pub primary_style_id: i64,
+    pub selected_perk_ids: Vec<i64>,
+    pub sub_style_id: i64,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PerkInventory {
+    pub custom_page_count: i64,
+    pub is_custom_page_creation_unlocked: bool,
+    /// Maximum number of editable pages the account can have.
+    pub owned_page_count: i64,
+}
+
+/// Body of a page create or update.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunePageInput {
+    pub name: String,
+    pub primary_style_id: i64,
+    pub sub_style_id: i64,
+    pub selected_perk_ids: Vec<i64>,
+    pub current: bool,
+}
+
+impl RunePageInput {
+    pub fn from_rune(rune: &Rune, prefix: &str) -> Self {
+        Self {
+            name: format!("{prefix}{}", rune.name),
+            primary_style_id: rune.primary_style_id,
+            sub_style_id: rune.sub_style_id,
+            selected_perk_ids: rune.selected_perk_ids.clone(),
+            current: true,
+        }
+    }
 }
 
-pub async fn get_champion_icon_by_id(endpoint: &String, id: i64) -> Result<Bytes, FetchError> {
-    let url = format!("{endpoint}/lol-game-data/assets/v1/champion-icons/{id}.png");
-    fetch_image_data(&url).await
+pub const CHAMPR_PAGE_PREFIX: &str = "ChampR ";
+
+/// Where [`LcuClient::apply_rune`] puts the page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuneApplyStrategy {
+    /// Overwrite a page whose name starts with `prefix`, create one if there
+    /// is none, and replace the current page when the inventory is full.
+    ReuseOwned { prefix: String },
+    /// Replace the current page, or the last deletable one.
+    ReplaceCurrent,
+    /// Only create new pages.
+    FailIfFull,
 }
 
-pub async fn make_ws_client(endpoint: &String) -> Result<WebSocket, reqwest_websocket::Error> {
-    let url = format!("wss://{endpoint}");
-    let client = Client::default();
-    let response = client.get(url).upgrade().send().await?;
-    let ws = response.into_websocket().await?;
-    Ok(ws)
+impl Default for RuneApplyStrategy {
+    fn default() -> Self {
+        RuneApplyStrategy::ReuseOwned {
+            prefix: CHAMPR_PAGE_PREFIX.to_string(),
+        }
+    }
 }
 
-pub fn make_sub_msg() -> Message {
-    Message::Text("[5, \"OnJsonApiEvent\"]".into())
+impl RuneApplyStrategy {
+    fn page_prefix(&self) -> &str {
+        match self {
+            RuneApplyStrategy::ReuseOwned { prefix } => prefix,
+            _ => "",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuneApplyPlan {
+    Update(i64),
+    Create,
+    Replace(i64),
 }
 
-pub fn make_champion_avatar_url(endpoint: &String, id: u64) -> Url {
-    format!("https://{endpoint}/lol-game-data/assets/v1/champion-icons/{id}.png").parse::<Url>().unwrap()
+pub fn plan_rune_apply(
+    pages: &[RunePage],
+    inventory: &PerkInventory,
+    strategy: &RuneApplyStrategy,
+) -> Result<RuneApplyPlan, LcuError> {
+    let deletable: Vec<&RunePage> = pages.iter().filter(|p| p.is_deletable).collect();
+    let has_room = (deletable.len() as i64) < inventory.owned_page_count;
+    // the current page first, otherwise the last deletable page
+    let replaceable = deletable
+        .iter()
+        .find(|p| p.current)
+        .or(deletable.last())
+        .map(|p| p.id);
+
+    match strategy {
+        RuneApplyStrategy::ReuseOwned { prefix } => {
+            if let Some(page) = pages
+                .iter()
+                .find(|p| p.is_editable && p.name.starts_with(prefix.as_str()))
+            {
+                return Ok(RuneApplyPlan::Update(page.id));
+            }
+            if has_room {
+                return Ok(RuneApplyPlan::Create);
+            }
+            replaceable
+                .map(RuneApplyPlan::Replace)
+                .ok_or(LcuError::PageLimitReached(inventory.owned_page_count))
+        }
+        RuneApplyStrategy::ReplaceCurrent => match replaceable {
+            Some(id) => Ok(RuneApplyPlan::Replace(id)),
+            None if has_room => Ok(RuneApplyPlan::Create),
+            None => Err(LcuError::PageLimitReached(inventory.owned_page_count)),
+        },
+        RuneApplyStrategy::FailIfFull => {
+            if has_room {
+                Ok(RuneApplyPlan::Create)
+            } else {
+                Err(LcuError::PageLimitReached(inventory.owned_page_count))
+            }
+        }
+    }
+}
+
+/// Options of [`LcuClient::appy_rune_and_builds`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApplyOptions {
+    pub rune_strategy: RuneApplyStrategy,
+    pub rune_check: RuneCheck,
+    pub flash: FlashPreference,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepOutcome {
+    Applied,
+    /// Nothing to apply, e.g. the build has no spells.
+    Skipped,
+    Failed(LcuError),
+}
+
+impl StepOutcome {
+    pub fn is_failed(&self) -> bool {
+        matches!(self, StepOutcome::Failed(_))
+    }
+}
+
+impl From<Result<(), LcuError>> for StepOutcome {
+    fn from(result: Result<(), LcuError>) -> Self {
+        match result {
+            Ok(()) => StepOutcome::Applied,
+            Err(e) => StepOutcome::Failed(e),
+        }
+    }
+}
+
+/// Result of [`LcuClient::appy_rune_and_builds`], one outcome per step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApplyReport {
+    pub rune: StepOutcome,
+    pub item_sets: StepOutcome,
+    pub spells: StepOutcome,
+}
+
+impl ApplyReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors().is_empty()
+    }
+
+    /// Failed steps by name.
+    pub fn errors(&self) -> Vec<(&'static str, &LcuError)> {
+        [
+            ("rune", &self.rune),
+            ("item_sets", &self.item_sets),
+            ("spells", &self.spells),
+        ]
+        .into_iter()
+        .filter_map(|(step, outcome)| match outcome {
+            StepOutcome::Failed(e) => Some((step, e)),
+            _ => None,
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockLcu;
+    use serde_json::json;
+
+    #[test]
+    fn build_client_from_credentials() {
+        let client = LcuClient::new("51234", "abc123").unwrap();
+        assert_eq!(client.base_url().as_str(), "https://127.0.0.1:51234/");
+        assert_eq!(
+            client.url("/lol-perks/v1/pages").as_str(),
+            "https://127.0.0.1:51234/lol-perks/v1/pages"
+        );
+        assert_eq!(
+            client.url("/lol-summoner/v2/summoners?ids=[1,2]").as_str(),
+            "https://127.0.0.1:51234/lol-summoner/v2/summoners?ids=[1,2]"
+        );
+        assert_eq!(
+            client.auth_header().to_str().unwrap(),
+            "Basic cmlvdDphYmMxMjM="
+        );
+
+        assert!(LcuClient::new("not a port", "abc123").is_err());
+    }
+
+    fn rune() -> Rune {
+        Rune {
+            name: "Ahri mid".to_string(),
+            primary_style_id: 8100,
+            sub_style_id: 8200,
+            selected_perk_ids: vec![8112, 8139, 8138, 8135, 8226, 8210, 5008, 5008, 5002],
+            ..Default::default()
+        }
+    }
+
+    fn change(method: &str, path: &str) -> (String, String) {
+        (method.to_string(), path.to_string())
+    }
+
+    #[tokio::test]
+    async fn get_session_from_mock() {
+        let mock = MockLcu::start().await;
+        assert_eq!(mock.client().get_session().await, Ok(Some(64)));
+
+        mock.respond("GET", "/lol-champ-select/v1/session", 404, json!({}));
+        assert!(matches!(
+            mock.client().get_session().await,
+            Err(LcuError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn keep_json_path_of_created_page() {
+        let mock = MockLcu::start().await;
+        mock.respond("POST", "/lol-perks/v1/pages", 200, json!({ "id": "50" }));
+        let page = RunePageInput::from_rune(&rune(), "[ChampR]");
+        assert!(matches!(
+            mock.client().create_rune_page(&page).await,
+            Err(LcuError::Deserialize { path, .. }) if path == "id"
+        ));
+    }
+
+    #[tokio::test]
+    async fn apply_rune_to_mock() {
+        let mock = MockLcu::start().await;
+        let client = mock.client();
+
+        // one of two pages is in use, a new one is created
+        client
+            .apply_rune(&rune(), &RuneApplyStrategy::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            mock.changes(),
+            vec![
+                change("POST", "/lol-perks/v1/pages"),
+                change("PUT", "/lol-perks/v1/currentpage"),
+            ]
+        );
+        let requests = mock.requests();
+        let created = requests.iter().find(|r| r.method == "POST").unwrap();
+        assert_eq!(created.body["name"], "ChampR Ahri mid");
+        assert_eq!(created.body["primaryStyleId"], 8100);
+        assert_eq!(requests.last().unwrap().body, json!(100));
+
+        // the inventory is full now
+        mock.respond(
+            "GET",
+            "/lol-perks/v1/inventory",
+            200,
+            json!({ "ownedPageCount": 1 }),
+        );
+        client
+            .apply_rune(&rune(), &RuneApplyStrategy::ReplaceCurrent)
+            .await
+            .unwrap();
+        assert_eq!(
+            mock.changes()[2..],
+            [
+                change("DELETE", "/lol-perks/v1/pages/10"),
+                change("POST", "/lol-perks/v1/pages"),
+                change("PUT", "/lol-perks/v1/currentpage"),
+            ]
+        );
+        assert_eq!(
+            client
+                .apply_rune(&rune(), &RuneApplyStrategy::FailIfFull)
+                .await,
+            Err(LcuError::PageLimitReached(1))
+        );
+
+        mock.respond(
+            "POST",
+            "/lol-perks/v1/pages",
+            400,
+            json!({ "errorCode": "RPC_ERROR", "httpStatus": 400, "message": "Invalid page" }),
+        );
+        assert!(matches!(
+            client
+                .apply_rune(&rune(), &RuneApplyStrategy::default())
+                .await,
+            Err(LcuError::Api { status: 400, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn apply_section_to_mock() {
+        let mock = MockLcu::start().await;
+        let section = BuildSection {
+            alias: "Ahri".to_string(),
+            spells: Some(vec!["SummonerDot".to_string(), "SummonerFlash".to_string()]),
+            item_builds: vec![ItemBuild {
+                title: "Ahri mid".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let options = ApplyOptions {
+            flash: FlashPreference::D,
+            ..Default::default()
+        };
+
+        let report = mock
+            .client()
+            .appy_rune_and_builds(&rune(), &section, "op.gg", &options)
+            .await;
+        assert!(report.is_ok(), "{:?}", report);
+
+        let requests = mock.requests();
+        let sets = requests
+            .iter()
+            .find(|r| r.method == "PUT" && r.path == "/lol-item-sets/v1/item-sets/1001/sets")
+            .unwrap();
+        assert_eq!(sets.body["itemSets"][0]["title"], "Ahri mid");
+        let spells = requests.last().unwrap();
+        assert_eq!(spells.path, "/lol-champ-select/v1/session/my-selection");
+        assert_eq!(spells.body, json!({ "spell1Id": 4, "spell2Id": 14 }));
+
+        // a failed step does not stop the others
+        mock.respond("GET", "/lol-perks/v1/pages", 500, json!({}));
+        let report = mock
+            .client()
+            .appy_rune_and_builds(&rune(), &section, "op.gg", &options)
+            .await;
+        assert!(report.rune.is_failed());
+        assert_eq!(report.item_sets, StepOutcome::Applied);
+        assert_eq!(report.spells, StepOutcome::Applied);
+    }
+
+    #[tokio::test]
+    async fn reject_invalid_rune_page() {
+        let mock = MockLcu::start().await;
+        mock.respond("GET", "/lol-perks/v1/styles", 200, json!([]));
+        mock.respond("GET", "/lol-perks/v1/perks", 200, json!([]));
+        let options = ApplyOptions {
+            rune_check: RuneCheck::Reject,
+            ..Default::default()
+        };
+
+        let report = mock
+            .client()
+            .appy_rune_and_builds(&rune(), &BuildSection::default(), "op.gg", &options)
+            .await;
+        let StepOutcome::Failed(LcuError::InvalidRunePage(issues)) = report.rune else {
+            panic!("{:?}", report);
+        };
+        assert!(issues.starts_with("unknown style 8100"), "{issues}");
+        assert!(mock.changes().is_empty());
+    }
+
+    #[test]
+    fn report_failed_steps() {
+        let report = ApplyReport {
+            rune: StepOutcome::Applied,
+            item_sets: StepOutcome::Failed(LcuError::Unauthorized),
+            spells: StepOutcome::Skipped,
+        };
+        assert!(!report.is_ok());
+        assert_eq!(
+            report.errors(),
+            vec![("item_sets", &LcuError::Unauthorized)]
+        );
+    }
+
+    fn page(id: i64, name: &str, current: bool, is_deletable: bool) -> RunePage {
+        RunePage {
+            id,
+            name: name.to_string(),
+            current,
+            is_deletable,
+            is_editable: is_deletable,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn plan_rune_pages() {
+        let inventory = PerkInventory {
+            owned_page_count: 3,
+            ..Default::default()
+        };
+        let reuse = RuneApplyStrategy::default();
+        let pages = vec![
+            page(1, "Domination", false, false),
+            page(10, "My page", true, true),
+            page(11, "ChampR Ahri", false, true),
+        ];
+
+        assert_eq!(
+            plan_rune_apply(&pages, &inventory, &reuse),
+            Ok(RuneApplyPlan::Update(11))
+        );
+        assert_eq!(
+            plan_rune_apply(&pages, &inventory, &RuneApplyStrategy::ReplaceCurrent),
+            Ok(RuneApplyPlan::Replace(10))
+        );
+        assert_eq!(
+            plan_rune_apply(&pages, &inventory, &RuneApplyStrategy::FailIfFull),
+            Ok(RuneApplyPlan::Create)
+        );
+
+        let full = vec![
+            page(1, "Domination", true, false),
+            page(10, "A", false, true),
+            page(11, "B", false, true),
+            page(12, "C", false, true),
+        ];
+        assert_eq!(
+            plan_rune_apply(&full, &inventory, &reuse),
+            Ok(RuneApplyPlan::Replace(12))
+        );
+        assert_eq!(
+            plan_rune_apply(&full, &inventory, &RuneApplyStrategy::FailIfFull),
+            Err(LcuError::PageLimitReached(3))
+        );
+
+        let empty = PerkInventory::default();
+        assert_eq!(
+            plan_rune_apply(&pages[..1], &empty, &RuneApplyStrategy::ReplaceCurrent),
+            Err(LcuError::PageLimitReached(0))
+        );
+    }
 }
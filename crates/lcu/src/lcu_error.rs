@@ -1,18 +1,208 @@
-#[derive(Debug, Clone)]
+use serde::Deserialize;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum LcuError {
+    /// Nothing listens on the port, usually the client was closed.
+    ConnectionRefused(String),
+    /// The token was rejected, the client restarted with new credentials.
+    Unauthorized,
+    /// The client refused the request at `path`, e.g. an action that is not
+    /// allowed in the current phase.
+    Forbidden(String),
+    NotFound(String),
+    /// The LCU answered with an error body, e.g. `RPC_ERROR`.
+    Api {
+        status: u16,
+        error_code: String,
+        message: String,
+    },
+    /// The response did not match the expected model at `path`.
+    Deserialize {
+        path: String,
+        message: String,
+    },
+    Timeout,
+    /// No rune page left to create or replace, holds the page capacity.
+    PageLimitReached(i64),
+    /// The rune page does not fit the client's styles, lists the issues.
+    InvalidRunePage(String),
     APIError(String),
 }
 
+/// Error body returned by the LCU, e.g.
+/// `{"errorCode":"RPC_ERROR","httpStatus":400,"message":"..."}`.
+#[derive(Default, Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LcuErrorBody {
+    #[serde(default)]
+    pub error_code: String,
+    #[serde(default)]
+    pub http_status: u16,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl LcuError {
+    /// Maps a non-success response of `path` to an error.
+    pub fn from_status(status: u16, path: &str, body: &[u8]) -> LcuError {
+        match status {
+            401 => LcuError::Unauthorized,
+            403 => LcuError::Forbidden(path.to_string()),
+            404 => LcuError::NotFound(path.to_string()),
+            _ => {
+                let body =
+                    serde_json::from_slice::<LcuErrorBody>(body).unwrap_or_else(|_| LcuErrorBody {
+                        message: String::from_utf8_lossy(body).into_owned(),
+                        ..Default::default()
+                    });
+                LcuError::Api {
+                    status,
+                    error_code: body.error_code,
+                    message: body.message,
+                }
+            }
+        }
+    }
+
+    /// Credentials are stale, callers should discover the client again.
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self, LcuError::Unauthorized)
+    }
+
+    /// The client refused the request itself, e.g. a champion that cannot be
+    /// picked, so sending it again will not help.
+    pub fn is_rejected(&self) -> bool {
+        match self {
+            LcuError::Forbidden(_) | LcuError::NotFound(_) => true,
+            LcuError::Api { status, .. } => (400..500).contains(status),
+            _ => false,
+        }
+    }
+
+    /// The client is not reachable anymore.
+    pub fn is_disconnected(&self) -> bool {
+        matches!(self, LcuError::ConnectionRefused(_) | LcuError::Timeout)
+    }
+}
+
+impl fmt::Display for LcuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LcuError::ConnectionRefused(msg) => write!(f, "connection refused: {msg}"),
+            LcuError::Unauthorized => write!(f, "unauthorized, the auth token is stale"),
+            LcuError::Forbidden(path) => write!(f, "request forbidden: {path}"),
+            LcuError::NotFound(path) => write!(f, "resource not found: {path}"),
+            LcuError::Api {
+                status,
+                error_code,
+                message,
+            } => write!(f, "lcu error {status} {error_code}: {message}"),
+            LcuError::Deserialize { path, message } => {
+                write!(f, "unexpected response at `{path}`: {message}")
+            }
+            LcuError::Timeout => write!(f, "request timed out"),
+            LcuError::PageLimitReached(count) => {
+                write!(f, "all {count} rune pages are in use")
+            }
+            LcuError::InvalidRunePage(issues) => write!(f, "invalid rune page: {issues}"),
+            LcuError::APIError(msg) => write!(f, "lcu api error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for LcuError {}
+
 impl From<reqwest::Error> for LcuError {
     fn from(error: reqwest::Error) -> LcuError {
-        println!("lcu api error: {:?}", error);
-        LcuError::APIError(error.to_string())
+        if error.is_timeout() {
+            LcuError::Timeout
+        } else if error.is_connect() {
+            LcuError::ConnectionRefused(error.to_string())
+        } else if let Some(status) = error.status() {
+            LcuError::from_status(status.as_u16(), error.url().map_or("", |u| u.path()), &[])
+        } else {
+            LcuError::APIError(error.to_string())
+        }
+    }
+}
+
+impl From<serde_path_to_error::Error<serde_json::Error>> for LcuError {
+    fn from(error: serde_path_to_error::Error<serde_json::Error>) -> LcuError {
+        LcuError::Deserialize {
+            path: error.path().to_string(),
+            message: error.into_inner().to_string(),
+        }
     }
 }
 
 impl From<anyhow::Error> for LcuError {
     fn from(error: anyhow::Error) -> LcuError {
-        println!("lcu api error: {:?}", error);
         LcuError::APIError(error.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_status_to_error() {
+        assert_eq!(
+            LcuError::from_status(401, "/lol-perks/v1/pages", b""),
+            LcuError::Unauthorized
+        );
+        assert_eq!(
+            LcuError::from_status(403, "/lol-champ-select/v1/session/actions/1", b""),
+            LcuError::Forbidden("/lol-champ-select/v1/session/actions/1".to_string())
+        );
+        assert_eq!(
+            LcuError::from_status(404, "/lol-champ-select/v1/session", b""),
+            LcuError::NotFound("/lol-champ-select/v1/session".to_string())
+        );
+        assert_eq!(
+            LcuError::from_status(
+                400,
+                "/lol-perks/v1/pages",
+                br#"{"errorCode":"RPC_ERROR","httpStatus":400,"implementationDetails":{},"message":"Max pages reached"}"#
+            ),
+            LcuError::Api {
+                status: 400,
+                error_code: "RPC_ERROR".to_string(),
+                message: "Max pages reached".to_string(),
+            }
+        );
+        assert_eq!(
+            LcuError::from_status(500, "/", b"oops"),
+            LcuError::Api {
+                status: 500,
+                error_code: String::new(),
+                message: "oops".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn tell_rejected_from_transient_errors() {
+        assert!(LcuError::from_status(400, "/", b"").is_rejected());
+        assert!(LcuError::from_status(403, "/", b"").is_rejected());
+        assert!(!LcuError::from_status(500, "/", b"").is_rejected());
+        assert!(!LcuError::from_status(401, "/", b"").is_rejected());
+        assert!(!LcuError::Timeout.is_rejected());
+    }
+
+    #[test]
+    fn keep_json_path_of_deserialize_error() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Page {
+            id: i64,
+        }
+
+        let mut de = serde_json::Deserializer::from_str(r#"[{"id":1},{"id":"2"}]"#);
+        let err: LcuError = serde_path_to_error::deserialize::<_, Vec<Page>>(&mut de)
+            .unwrap_err()
+            .into();
+        assert!(matches!(err, LcuError::Deserialize { path, .. } if path == "[1].id"));
+    }
+}
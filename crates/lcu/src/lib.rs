@@ -1,10 +1,27 @@
 pub mod lcu_api;
+pub mod asset_cache;
 pub mod builds;
+pub mod champ_select;
 pub mod cmd;
 pub mod constants;
+pub mod discovery_error;
+pub mod events;
+pub mod game_mode;
+pub mod gameflow;
+pub mod install_dir;
+pub mod item_sets;
 pub mod lcu_error;
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock;
+pub mod pick_ban;
+pub mod ready_check;
+pub mod recording;
+pub mod region;
+pub mod rune_validator;
 pub mod source;
+pub mod spells;
 pub mod task;
+pub mod watcher;
 pub mod web;
 
 pub use reqwest;
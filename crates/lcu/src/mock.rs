@@ -0,0 +1,490 @@
+//! A fake League client for tests: HTTPS with scripted responses plus the
+//! WAMP websocket, on a random local port.
+//!
+//! Other crates get it through the `test-support` feature, e.g. as a
+//! dev-dependency `lcu = { path = "../lcu", features = ["test-support"] }`.
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::Incoming,
+    header::{self, HeaderValue},
+    service::service_fn,
+    Request, Response, StatusCode,
+};
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use serde_json::{json, Value};
+use tokio::{net::TcpListener, sync::broadcast, task::JoinHandle};
+use tokio_rustls::{
+    rustls::{
+        self,
+        pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    },
+    TlsAcceptor,
+};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+use crate::{
+    cmd::{self, CommandLineOutput},
+    events::{EventType, LcuEvent, JSON_API_EVENT},
+    lcu_api::LcuClient,
+};
+
+pub const MOCK_TOKEN: &str = "mock-token";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    /// `Value::Null` for an empty body.
+    pub body: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+#[derive(Clone)]
+enum WsCommand {
+    Event(LcuEvent),
+    Close,
+}
+
+#[derive(Default)]
+struct State {
+    routes: HashMap<(String, String), MockResponse>,
+    requests: Vec<RecordedRequest>,
+    /// Topics of all open websockets.
+    subscriptions: Vec<String>,
+}
+
+pub struct MockLcu {
+    port: u16,
+    state: Arc<Mutex<State>>,
+    ws: broadcast::Sender<WsCommand>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for MockLcu {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl MockLcu {
+    /// Starts the server with responses for the summoner, perks, champ select
+    /// and gameflow endpoints.
+    pub async fn start() -> MockLcu {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = tls_acceptor();
+        let state = Arc::new(Mutex::new(State::default()));
+        let (ws, _) = broadcast::channel(64);
+
+        let task = {
+            let state = state.clone();
+            let ws = ws.clone();
+            tokio::spawn(async move {
+                loop {
+                    let Ok((stream, _)) = listener.accept().await else {
+                        continue;
+                    };
+                    let acceptor = acceptor.clone();
+                    let state = state.clone();
+                    let ws = ws.clone();
+                    tokio::spawn(async move {
+                        let Ok(stream) = acceptor.accept(stream).await else {
+                            return;
+                        };
+                        let service = service_fn(move |req| handle(req, state.clone(), ws.clone()));
+                        let _ = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
+                            .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                            .await;
+                    });
+                }
+            })
+        };
+
+        let mock = MockLcu {
+            port,
+            state,
+            ws,
+            task,
+        };
+        mock.script_defaults();
+        mock
+    }
+
+    fn script_defaults(&self) {
+        self.respond(
+            "GET",
+            "/lol-summoner/v1/current-summoner",
+            200,
+            json!({
+                "accountId": 2001,
+                "displayName": "Mock",
+                "gameName": "Mock",
+                "internalName": "Mock",
+                "nameChangeFlag": false,
+                "percentCompleteForNextLevel": 50,
+                "privacy": "PUBLIC",
+                "profileIconId": 29,
+                "puuid": "mock-puuid",
+                "rerollPoints": {
+                    "currentPoints": 0,
+                    "maxRolls": 2,
+                    "numberOfRolls": 0,
+                    "pointsCostToRoll": 250,
+                    "pointsToReroll": 250,
+                },
+                "summonerId": 1001,
+                "summonerLevel": 30,
+                "tagLine": "MOCK",
+                "unnamed": false,
+                "xpSinceLastLevel": 100,
+                "xpUntilNextLevel": 200,
+            }),
+        );
+        self.respond(
+            "GET",
+            "/lol-champ-select/v1/session",
+            200,
+            serde_json::from_str(include_str!("../fixtures/champ_select/draft.json")).unwrap(),
+        );
+        self.respond(
+            "GET",
+            "/lol-gameflow/v1/gameflow-phase",
+            200,
+            json!("ChampSelect"),
+        );
+        self.respond(
+            "GET",
+            "/lol-gameflow/v1/session",
+            200,
+            json!({
+                "phase": "ChampSelect",
+                "gameData": { "gameId": 1, "queue": { "id": 420, "mapId": 11, "gameMode": "CLASSIC" } },
+                "map": { "id": 11, "gameMode": "CLASSIC" },
+            }),
+        );
+        self.respond(
+            "GET",
+            "/lol-perks/v1/pages",
+            200,
+            json!([
+                { "id": 1, "name": "Domination", "isDeletable": false, "isEditable": false },
+                { "id": 10, "name": "My page", "current": true, "isDeletable": true, "isEditable": true },
+            ]),
+        );
+        self.respond(
+            "GET",
+            "/lol-perks/v1/inventory",
+            200,
+            json!({ "ownedPageCount": 2, "customPageCount": 1 }),
+        );
+        self.respond(
+            "POST",
+            "/lol-perks/v1/pages",
+            200,
+            json!({ "id": 100, "isDeletable": true, "isEditable": true }),
+        );
+        self.respond(
+            "GET",
+            "/lol-item-sets/v1/item-sets/1001/sets",
+            200,
+            json!({ "accountId": 2001, "itemSets": [], "timestamp": 0 }),
+        );
+    }
+
+    /// Answers `method` `path` with `status` and `body` from now on. Other
+    /// `PUT`, `PATCH` and `DELETE` requests get a `204`, other `GET` and `POST`
+    /// requests a `404`.
+    pub fn respond(&self, method: &str, path: &str, status: u16, body: Value) {
+        self.state.lock().unwrap().routes.insert(
+            (method.to_string(), path.to_string()),
+            MockResponse { status, body },
+        );
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn client(&self) -> LcuClient {
+        LcuClient::from_cmd_output(&self.cmd_output()).unwrap()
+    }
+
+    pub fn cmd_output(&self) -> CommandLineOutput {
+        let port = self.port.to_string();
+        let token = MOCK_TOKEN.to_string();
+        CommandLineOutput {
+            pid: std::process::id(),
+            auth_url: cmd::make_auth_url(&token, &port),
+            token,
+            port,
+            ..Default::default()
+        }
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Requests other than `GET`, in the order they were received.
+    pub fn changes(&self) -> Vec<(String, String)> {
+        self.requests()
+            .into_iter()
+            .filter(|r| r.method != "GET")
+            .map(|r| (r.method, r.path))
+            .collect()
+    }
+
+    pub fn subscriptions(&self) -> Vec<String> {
+        self.state.lock().unwrap().subscriptions.clone()
+    }
+
+    /// Waits until a websocket subscribed to `topic`.
+    pub async fn wait_for_subscription(&self, topic: &str) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !self.subscriptions().iter().any(|t| t == topic) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("no subscription to {topic}"));
+    }
+
+    /// Sends an event to every websocket subscribed to a topic covering `uri`.
+    pub fn emit(&self, uri: &str, event_type: EventType, data: Value) {
+        let _ = self.ws.send(WsCommand::Event(LcuEvent {
+            uri: uri.to_string(),
+            event_type,
+            data,
+        }));
+    }
+
+    /// Closes all websockets, as a restarting client would.
+    pub fn close_websockets(&self) {
+        let _ = self.ws.send(WsCommand::Close);
+    }
+}
+
+fn tls_acceptor() -> TlsAcceptor {
+    let cert = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()]).unwrap();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(cert.key_pair.serialize_der()));
+    let chain = vec![CertificateDer::from(cert.cert.der().to_vec())];
+
+    let mut config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(chain, key)
+    .unwrap();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    TlsAcceptor::from(Arc::new(config))
+}
+
+fn response(status: u16, body: &Value) -> Response<Full<Bytes>> {
+    let body = match body {
+        Value::Null => Bytes::new(),
+        body => Bytes::from(body.to_string()),
+    };
+    let mut resp = Response::new(Full::new(body));
+    *resp.status_mut() = StatusCode::from_u16(status).unwrap();
+    resp.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    resp
+}
+
+async fn handle(
+    req: Request<Incoming>,
+    state: Arc<Mutex<State>>,
+    ws: broadcast::Sender<WsCommand>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let expected = LcuClient::new("1", MOCK_TOKEN)
+        .unwrap()
+        .auth_header()
+        .clone();
+    if req.headers().get(header::AUTHORIZATION) != Some(&expected) {
+        return Ok(response(
+            401,
+            &json!({ "errorCode": "UNAUTHORIZED", "httpStatus": 401, "message": "" }),
+        ));
+    }
+
+    if req.headers().contains_key(header::SEC_WEBSOCKET_KEY) {
+        return Ok(upgrade(req, state, ws));
+    }
+
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let body = req.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+    let mut state = state.lock().unwrap();
+    state.requests.push(RecordedRequest {
+        method: method.clone(),
+        path: path.clone(),
+        body,
+    });
+    let resp = match state.routes.get(&(method.clone(), path.clone())) {
+        Some(MockResponse { status, body }) => response(*status, body),
+        None if matches!(method.as_str(), "PUT" | "PATCH" | "DELETE") => {
+            response(204, &Value::Null)
+        }
+        None => response(
+            404,
+            &json!({
+                "errorCode": "RPC_ERROR",
+                "httpStatus": 404,
+                "message": format!("Invalid URI format: {path}"),
+            }),
+        ),
+    };
+    Ok(resp)
+}
+
+fn upgrade(
+    mut req: Request<Incoming>,
+    state: Arc<Mutex<State>>,
+    ws: broadcast::Sender<WsCommand>,
+) -> Response<Full<Bytes>> {
+    let key = req.headers()[header::SEC_WEBSOCKET_KEY].as_bytes().to_vec();
+    let commands = ws.subscribe();
+
+    tokio::spawn(async move {
+        let Ok(upgraded) = hyper::upgrade::on(&mut req).await else {
+            return;
+        };
+        let socket =
+            WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+        serve_wamp(socket, state, commands).await;
+    });
+
+    let mut resp = Response::new(Full::new(Bytes::new()));
+    *resp.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+    let headers = resp.headers_mut();
+    headers.insert(header::CONNECTION, HeaderValue::from_static("Upgrade"));
+    headers.insert(header::UPGRADE, HeaderValue::from_static("websocket"));
+    headers.insert(
+        header::SEC_WEBSOCKET_ACCEPT,
+        HeaderValue::from_str(&derive_accept_key(&key)).unwrap(),
+    );
+    resp
+}
+
+async fn serve_wamp<S>(
+    mut socket: WebSocketStream<S>,
+    state: Arc<Mutex<State>>,
+    mut commands: broadcast::Receiver<WsCommand>,
+) where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let mut topics: Vec<String> = vec![];
+
+    'serve: loop {
+        tokio::select! {
+            message = socket.next() => {
+                let Some(Ok(Message::Text(text))) = message else {
+                    break 'serve;
+                };
+                let Ok((opcode, topic)) = serde_json::from_str::<(u8, String)>(&text) else {
+                    continue;
+                };
+                let mut state = state.lock().unwrap();
+                match opcode {
+                    5 => {
+                        topics.push(topic.clone());
+                        state.subscriptions.push(topic);
+                    }
+                    6 => {
+                        topics.retain(|t| t != &topic);
+                        if let Some(i) = state.subscriptions.iter().position(|t| t == &topic) {
+                            state.subscriptions.remove(i);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            command = commands.recv() => match command {
+                Ok(WsCommand::Event(event)) => {
+                    // like the client, only `OnJsonApiEvent` carries events
+                    for topic in topics.iter().filter(|t| *t == JSON_API_EVENT) {
+                        let text = json!([8, topic, event]).to_string();
+                        if socket.send(Message::text(text)).await.is_err() {
+                            break 'serve;
+                        }
+                    }
+                }
+                Ok(WsCommand::Close) | Err(_) => {
+                    let _ = socket.close(None).await;
+                    break 'serve;
+                }
+            },
+        }
+    }
+
+    let mut state = state.lock().unwrap();
+    for topic in topics {
+        if let Some(i) = state.subscriptions.iter().position(|t| t == &topic) {
+            state.subscriptions.remove(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn record_requests() {
+        let mock = MockLcu::start().await;
+        let client = mock.client();
+
+        let summoner = client.get_current_summoner().await.unwrap();
+        assert_eq!(summoner.summoner_id, 1001);
+        client.set_summoner_spells(4, 14).await.unwrap();
+        assert!(client
+            .get("/lol-missing/v1/thing")
+            .await
+            .map(|_: Value| ())
+            .unwrap_err()
+            .to_string()
+            .contains("/lol-missing/v1/thing"));
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(
+            requests[1],
+            RecordedRequest {
+                method: "PATCH".to_string(),
+                path: "/lol-champ-select/v1/session/my-selection".to_string(),
+                body: json!({ "spell1Id": 4, "spell2Id": 14 }),
+            }
+        );
+
+        let stale = LcuClient::new(&mock.port().to_string(), "old-token").unwrap();
+        assert!(stale
+            .get_current_summoner()
+            .await
+            .unwrap_err()
+            .is_unauthorized());
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::{HashMap, HashSet};
+
+use futures::{Stream, StreamExt};
+use kv_log_macro::{info, warn};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    champ_select::{Action, ChampSelectSession},
+    events::{ConnectionEvent, EventInput, EventType, LcuEventClient},
+    lcu_api::LcuClient,
+};
+
+pub const CHAMP_SELECT_SESSION_URI: &str = "/lol-champ-select/v1/session";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PickMode {
+    /// Only hover, the user locks in.
+    #[default]
+    Hover,
+    Lock,
+}
+
+/// Champion ids in order of preference, per assigned position such as
+/// `jungle` or `utility`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChampionPreferences {
+    pub positions: HashMap<String, Vec<i64>>,
+    /// Used after the position list, and alone in blind pick.
+    pub fallback: Vec<i64>,
+}
+
+impl ChampionPreferences {
+    pub fn new(fallback: Vec<i64>) -> Self {
+        Self {
+            positions: HashMap::new(),
+            fallback,
+        }
+    }
+
+    pub fn position(mut self, position: &str, champion_ids: Vec<i64>) -> Self {
+        self.positions
+            .insert(position.to_ascii_lowercase(), champion_ids);
+        self
+    }
+
+    pub fn for_position<'a>(&'a self, position: Option<&str>) -> impl Iterator<Item = i64> + 'a {
+        let listed = position
+            .and_then(|p| self.positions.get(&p.to_ascii_lowercase()))
+            .into_iter()
+            .flatten();
+        listed.chain(self.fallback.iter()).copied()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PickBanConfig {
+    pub mode: PickMode,
+    pub picks: ChampionPreferences,
+    pub bans: ChampionPreferences,
+}
+
+/// What to send for the local player's current action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlannedAction {
+    pub action_id: i64,
+    pub champion_id: i64,
+    pub lock: bool,
+}
+
+impl PickBanConfig {
+    /// The next step for the local player's turn, `None` if it is not their
+    /// turn, no preferred champion is available or nothing is left to do.
+    ///
+    /// `playable` limits picks to those champions, bans are not limited.
+    /// Champions in `skip` are not tried, e.g. after the client rejected them.
+    pub fn plan(
+        &self,
+        session: &ChampSelectSession,
+        playable: Option<&HashSet<i64>>,
+        skip: &HashSet<i64>,
+    ) -> Option<PlannedAction> {
+        let action = session.my_current_action()?;
+        let unavailable = unavailable_champions(session, action);
+        let position = session.my_assigned_position();
+
+        let champion_id = if action.is_ban() {
+            self.bans
+                .for_position(position)
+                .find(|id| !unavailable.contains(id) && !skip.contains(id))?
+        } else {
+            self.picks.for_position(position).find(|id| {
+                !unavailable.contains(id)
+                    && !skip.contains(id)
+                    && match playable {
+                        Some(playable) => playable.contains(id),
+                        None => true,
+                    }
+            })?
+        };
+
+        let lock = self.mode == PickMode::Lock;
+        if !lock && action.champion_id == champion_id {
+            return None;
+        }
+        Some(PlannedAction {
+            action_id: action.id,
+            champion_id,
+            lock,
+        })
+    }
+}
+
+/// Champions that cannot be picked, or should not be banned, in `action`.
+/// Teammates' picks and intents count too, the local player's own hover does
+/// not.
+fn unavailable_champions(session: &ChampSelectSession, action: &Action) -> HashSet<i64> {
+    let me = session.local_player_cell_id;
+    let teammates = session
+        .my_team
+        .iter()
+        .filter(|m| m.cell_id != me)
+        .flat_map(|m| [m.champion_id, m.champion_pick_intent]);
+    let picked = session
+        .actions
+        .iter()
+        .flatten()
+        .filter(|a| a.is_pick() && a.completed && a.id != action.id)
+        .map(|a| a.champion_id);
+
+    session
+        .banned_champions()
+        .into_iter()
+        .chain(session.their_picks())
+        .chain(teammates)
+        .chain(picked)
+        .filter(|id| *id > 0)
+        .collect()
+}
+
+/// Hovers or locks champions from [`PickBanConfig`] whenever it is the local
+/// player's pick or ban turn.
+pub struct AutoPickBan {
+    config: watch::Sender<PickBanConfig>,
+    task: JoinHandle<()>,
+}
+
+impl AutoPickBan {
+    pub fn spawn(events: &LcuEventClient, config: PickBanConfig) -> AutoPickBan {
+        AutoPickBan::follow(events.inputs(CHAMP_SELECT_SESSION_URI), config)
+    }
+
+    /// Follows `inputs` instead of a live client, e.g. a replayed
+    /// [`Recording`](crate::recording::Recording).
+    pub fn follow<S>(inputs: S, config: PickBanConfig) -> AutoPickBan
+    where
+        S: Stream<Item = EventInput> + Unpin + Send + 'static,
+    {
+        let (config, rx) = watch::channel(config);
+        let task = tokio::spawn(run(inputs, rx));
+        AutoPickBan { config, task }
+    }
+
+    pub fn config(&self) -> PickBanConfig {
+        self.config.borrow().clone()
+    }
+
+    /// Applies from the next champ select update on.
+    pub fn set_config(&self, config: PickBanConfig) {
+        self.config.send_replace(config);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+/// State of one champ select.
+#[derive(Default)]
+struct Game {
+    game_id: i64,
+    /// Read once per game, the free rotation may have changed.
+    playable: Option<HashSet<i64>>,
+    /// Champions the client refused in this game.
+    rejected: HashSet<i64>,
+    last: Option<PlannedAction>,
+}
+
+async fn run<S>(mut inputs: S, config: watch::Receiver<PickBanConfig>)
+where
+    S: Stream<Item = EventInput> + Unpin,
+{
+    let mut client: Option<LcuClient> = None;
+    let mut game: Option<Game> = None;
+
+    while let Some(input) = inputs.next().await {
+        let session = match input {
+            EventInput::Event(event) if event.event_type == EventType::Delete => {
+                game = None;
+                continue;
+            }
+            EventInput::Event(event) => match event.data_as::<ChampSelectSession>() {
+                Ok(session) => session,
+                Err(e) => {
+                    warn!("[pick-ban] {}", e);
+                    continue;
+                }
+            },
+            EventInput::Connection(ConnectionEvent::Connected(c)) => {
+                client = Some(c);
+                continue;
+            }
+            EventInput::Connection(ConnectionEvent::Disconnected) => {
+                client = None;
+                game = None;
+                continue;
+            }
+        };
+        let Some(client) = &client else {
+            continue;
+        };
+
+        let game = match &mut game {
+            Some(game) if game.game_id == session.game_id => game,
+            _ => {
+                let playable = match client.list_playable_champion_ids().await {
+                    Ok(ids) => Some(ids),
+                    Err(e) => {
+                        warn!("[pick-ban] error listing owned champions: {}", e);
+                        None
+                    }
+                };
+                game.insert(Game {
+                    game_id: session.game_id,
+                    playable,
+                    ..Default::default()
+                })
+            }
+        };
+
+        let plan = config
+            .borrow()
+            .plan(&session, game.playable.as_ref(), &game.rejected);
+        let Some(plan) = plan else {
+            continue;
+        };
+        // the session echoes our own change before it settles
+        if game.last == Some(plan) {
+            continue;
+        }
+
+        let verb = if plan.lock { "locking" } else { "hovering" };
+        info!(
+            "[pick-ban] {} {} in action {}",
+            verb, plan.champion_id, plan.action_id
+        );
+        match client
+            .update_action(plan.action_id, plan.champion_id, plan.lock)
+            .await
+        {
+            Ok(()) => game.last = Some(plan),
+            Err(e) if e.is_rejected() => {
+                warn!("[pick-ban] {} {} rejected: {}", verb, plan.champion_id, e);
+                game.rejected.insert(plan.champion_id);
+            }
+            // tried again on the next session update
+            Err(e) => warn!("[pick-ban] {} {} failed: {}", verb, plan.champion_id, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::LcuEvent,
+        lcu_api::{Ownership, SummonerChampion},
+        mock::{MockLcu, RecordedRequest},
+    };
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn draft() -> ChampSelectSession {
+        serde_json::from_str(include_str!("../fixtures/champ_select/draft.json")).unwrap()
+    }
+
+    fn config(mode: PickMode) -> PickBanConfig {
+        PickBanConfig {
+            mode,
+            picks: ChampionPreferences::new(vec![11]).position("jungle", vec![122, 266, 64, 120]),
+            bans: ChampionPreferences::new(vec![555, 157, 23]),
+        }
+    }
+
+    #[test]
+    fn pick_by_position() {
+        let session = draft();
+        let none = HashSet::new();
+        // 122 and 266 are picked, 64 is our own hover
+        assert_eq!(config(PickMode::Hover).plan(&session, None, &none), None);
+        assert_eq!(
+            config(PickMode::Lock).plan(&session, None, &none),
+            Some(PlannedAction {
+                action_id: 24,
+                champion_id: 64,
+                lock: true
+            })
+        );
+
+        let playable = HashSet::from([11, 120]);
+        assert_eq!(
+            config(PickMode::Hover).plan(&session, Some(&playable), &none),
+            Some(PlannedAction {
+                action_id: 24,
+                champion_id: 120,
+                lock: false
+            })
+        );
+        assert_eq!(
+            config(PickMode::Hover)
+                .plan(&session, Some(&playable), &HashSet::from([120]))
+                .unwrap()
+                .champion_id,
+            11
+        );
+
+        let mut blind = session.clone();
+        blind.my_team[1].assigned_position = String::new();
+        assert_eq!(
+            config(PickMode::Hover)
+                .plan(&blind, None, &none)
+                .unwrap()
+                .champion_id,
+            11
+        );
+    }
+
+    #[test]
+    fn ban_available_champion() {
+        let mut session = draft();
+        let me = &mut session.actions[0][1];
+        me.completed = false;
+        me.is_in_progress = true;
+        me.champion_id = 0;
+        session.bans.my_team_bans = vec![238];
+        session.my_team[2].champion_pick_intent = 157;
+
+        // not owning a champion does not matter for bans
+        let playable = HashSet::new();
+        assert_eq!(
+            config(PickMode::Lock).plan(&session, Some(&playable), &HashSet::new()),
+            Some(PlannedAction {
+                action_id: 2,
+                champion_id: 555,
+                lock: true
+            })
+        );
+
+        session.bans.their_team_bans.push(555);
+        assert_eq!(
+            config(PickMode::Lock)
+                .plan(&session, None, &HashSet::new())
+                .unwrap()
+                .champion_id,
+            23
+        );
+    }
+
+    async fn mock_champ_select() -> MockLcu {
+        let mock = MockLcu::start().await;
+        let champion = |id: i64, owned: bool| SummonerChampion {
+            id,
+            ownership: Ownership {
+                owned,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        mock.respond(
+            "GET",
+            "/lol-champions/v1/inventories/1001/champions",
+            200,
+            json!([champion(11, true), champion(64, false), champion(120, true)]),
+        );
+        mock.respond(
+            "PATCH",
+            "/lol-champ-select/v1/session/actions/24",
+            204,
+            json!(null),
+        );
+        mock
+    }
+
+    fn session_update() -> EventInput {
+        EventInput::Event(LcuEvent {
+            uri: CHAMP_SELECT_SESSION_URI.to_string(),
+            event_type: EventType::Update,
+            data: serde_json::to_value(draft()).unwrap(),
+        })
+    }
+
+    async fn wait_for_patches(mock: &MockLcu, count: usize) -> Vec<RecordedRequest> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let patches = mock
+                    .requests()
+                    .into_iter()
+                    .filter(|r| r.method == "PATCH")
+                    .collect::<Vec<_>>();
+                if patches.len() >= count {
+                    return patches;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn lock_on_my_turn() {
+        let mock = mock_champ_select().await;
+        let (tx, inputs) = futures::channel::mpsc::unbounded();
+        let _auto = AutoPickBan::follow(inputs, config(PickMode::Lock));
+        tx.unbounded_send(EventInput::Connection(ConnectionEvent::Connected(mock.client())))
+            .unwrap();
+        for _ in 0..2 {
+            tx.unbounded_send(session_update()).unwrap();
+        }
+
+        let patch = wait_for_patches(&mock, 1).await.remove(0);
+        assert_eq!(patch.path, "/lol-champ-select/v1/session/actions/24");
+        assert_eq!(patch.body, json!({ "championId": 120, "completed": true }));
+
+        // the repeated session does not lock twice
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(mock.changes().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retry_after_transient_error() {
+        let mock = mock_champ_select().await;
+        let path = "/lol-champ-select/v1/session/actions/24";
+        mock.respond("PATCH", path, 500, json!({ "message": "busy" }));
+
+        let (tx, inputs) = futures::channel::mpsc::unbounded();
+        let _auto = AutoPickBan::follow(inputs, config(PickMode::Lock));
+        tx.unbounded_send(EventInput::Connection(ConnectionEvent::Connected(mock.client())))
+            .unwrap();
+        tx.unbounded_send(session_update()).unwrap();
+        wait_for_patches(&mock, 1).await;
+
+        // the same champion is tried again
+        mock.respond("PATCH", path, 204, json!(null));
+        tx.unbounded_send(session_update()).unwrap();
+        let patches = wait_for_patches(&mock, 2).await;
+        assert_eq!(patches[1].body, patches[0].body);
+
+    }
+
+    #[tokio::test]
+    async fn skip_rejected_champion() {
+        let mock = mock_champ_select().await;
+        let path = "/lol-champ-select/v1/session/actions/24";
+        mock.respond("PATCH", path, 400, json!({ "message": "not available" }));
+
+        let (tx, inputs) = futures::channel::mpsc::unbounded();
+        let _auto = AutoPickBan::follow(inputs, config(PickMode::Lock));
+        tx.unbounded_send(EventInput::Connection(ConnectionEvent::Connected(mock.client())))
+            .unwrap();
+        tx.unbounded_send(session_update()).unwrap();
+        wait_for_patches(&mock, 1).await;
+        tx.unbounded_send(session_update()).unwrap();
+        let patches = wait_for_patches(&mock, 2).await;
+        assert_eq!(patches[0].body["championId"], 120);
+        assert_eq!(patches[1].body["championId"], 11);
+    }
+}
@@ -0,0 +1,414 @@
+use std::time::Duration;
+
+use chrono::Timelike;
+use futures::{Stream, StreamExt};
+use kv_log_macro::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::watch, task::JoinHandle, time::Instant};
+
+use crate::{
+    events::{ConnectionEvent, EventInput, EventType, LcuEventClient},
+    lcu_api::LcuClient,
+};
+
+pub const READY_CHECK_URI: &str = "/lol-matchmaking/v1/ready-check";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReadyCheckState {
+    /// No queue popped.
+    #[default]
+    Invalid,
+    InProgress,
+    EveryoneReady,
+    StrangerNotReady,
+    PartyNotReady,
+    Error,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlayerResponse {
+    #[default]
+    None,
+    Accepted,
+    Declined,
+    #[serde(other)]
+    Unknown,
+}
+
+/// `/lol-matchmaking/v1/ready-check`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReadyCheck {
+    pub state: ReadyCheckState,
+    pub player_response: PlayerResponse,
+    /// Seconds since the queue popped.
+    pub timer: f64,
+}
+
+impl ReadyCheck {
+    /// Popped and not answered yet.
+    pub fn is_pending(&self) -> bool {
+        self.state == ReadyCheckState::InProgress && self.player_response == PlayerResponse::None
+    }
+}
+
+/// Declines instead of accepting while it applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeclineRule {
+    /// Local hours from `from` up to `to` exclusive, wrapping around
+    /// midnight when `to` is smaller, e.g. 23 to 7.
+    Hours { from: u32, to: u32 },
+}
+
+impl DeclineRule {
+    fn applies(&self, hour: u32) -> bool {
+        match self {
+            DeclineRule::Hours { from, to } if from <= to => (*from..*to).contains(&hour),
+            DeclineRule::Hours { from, to } => hour >= *from || hour < *to,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoAcceptConfig {
+    pub enabled: bool,
+    /// Time to wait before accepting, leaves room to answer by hand.
+    pub delay: Duration,
+    pub decline_rules: Vec<DeclineRule>,
+}
+
+impl Default for AutoAcceptConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delay: Duration::from_secs(2),
+            decline_rules: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Accept { after: Duration },
+    Decline(DeclineRule),
+    Ignore(&'static str),
+}
+
+impl AutoAcceptConfig {
+    /// What to do about `check` at local `hour`.
+    pub fn decide(&self, check: &ReadyCheck, hour: u32) -> Decision {
+        if !self.enabled {
+            return Decision::Ignore("auto accept is disabled");
+        }
+        if !check.is_pending() {
+            return Decision::Ignore("no pending ready check");
+        }
+        match self.decline_rules.iter().find(|r| r.applies(hour)) {
+            Some(rule) => Decision::Decline(rule.clone()),
+            None => Decision::Accept { after: self.delay },
+        }
+    }
+}
+
+/// Answers ready checks on behalf of the user, off until [`enable`]d.
+///
+/// Every ready check is decided once. An accept waits for the configured
+/// delay and is dropped if the check was answered, cancelled or the
+/// automation disabled in the meantime.
+///
+/// [`enable`]: AutoAccept::enable
+pub struct AutoAccept {
+    config: watch::Sender<AutoAcceptConfig>,
+    task: JoinHandle<()>,
+}
+
+impl AutoAccept {
+    pub fn spawn(events: &LcuEventClient, config: AutoAcceptConfig) -> AutoAccept {
+        AutoAccept::follow(events.inputs(READY_CHECK_URI), config)
+    }
+
+    /// Follows `inputs` instead of a live client, e.g. a replayed
+    /// [`Recording`](crate::recording::Recording).
+    pub fn follow<S>(inputs: S, config: AutoAcceptConfig) -> AutoAccept
+    where
+        S: Stream<Item = EventInput> + Unpin + Send + 'static,
+    {
+        let (config, rx) = watch::channel(config);
+        let task = tokio::spawn(run(inputs, rx));
+        AutoAccept { config, task }
+    }
+
+    pub fn enable(&self) {
+        info!("[ready-check] auto accept enabled");
+        self.config.send_modify(|c| c.enabled = true);
+    }
+
+    pub fn disable(&self) {
+        info!("[ready-check] auto accept disabled");
+        self.config.send_modify(|c| c.enabled = false);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.borrow().enabled
+    }
+
+    pub fn config(&self) -> AutoAcceptConfig {
+        self.config.borrow().clone()
+    }
+
+    pub fn set_config(&self, config: AutoAcceptConfig) {
+        self.config.send_replace(config);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+async fn run<S>(mut inputs: S, mut config: watch::Receiver<AutoAcceptConfig>)
+where
+    S: Stream<Item = EventInput> + Unpin,
+{
+    let mut client: Option<LcuClient> = None;
+    // whether the current ready check was decided already
+    let mut decided = false;
+    let mut accept_at: Option<Instant> = None;
+
+    loop {
+        let deadline = accept_at.unwrap_or_else(|| Instant::now() + Duration::from_secs(3600));
+        let check: ReadyCheck = tokio::select! {
+            input = inputs.next() => match input {
+                Some(EventInput::Event(event)) => match event.event_type {
+                    EventType::Delete => ReadyCheck::default(),
+                    _ => event.data_as().unwrap_or_default(),
+                },
+                Some(EventInput::Connection(ConnectionEvent::Connected(c))) => {
+                    let check = c.get_ready_check().await.unwrap_or_default();
+                    client = Some(c);
+                    check
+                }
+                Some(EventInput::Connection(ConnectionEvent::Disconnected)) => {
+                    client = None;
+                    ReadyCheck::default()
+                }
+                None => return,
+            },
+            changed = config.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                if accept_at.is_some() && !config.borrow().enabled {
+                    info!("[ready-check] disabled, not accepting");
+                    accept_at = None;
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(deadline), if accept_at.is_some() => {
+                accept_at = None;
+                if let Some(client) = &client {
+                    answer(client, true).await;
+                }
+                continue;
+            }
+        };
+
+        if check.state != ReadyCheckState::InProgress {
+            if accept_at.take().is_some() {
+                info!("[ready-check] ended as {:?} before accepting", check.state);
+            }
+            decided = false;
+            continue;
+        }
+        if !check.is_pending() {
+            if accept_at.take().is_some() {
+                info!(
+                    "[ready-check] answered by hand: {:?}",
+                    check.player_response
+                );
+            }
+            decided = true;
+            continue;
+        }
+        if decided {
+            continue;
+        }
+
+        decided = true;
+        let decision = config.borrow().decide(&check, chrono::Local::now().hour());
+        match decision {
+            Decision::Accept { after } => {
+                info!("[ready-check] accepting in {:?}", after);
+                accept_at = Some(Instant::now() + after);
+            }
+            Decision::Decline(rule) => {
+                info!("[ready-check] declining, matched {:?}", rule);
+                if let Some(client) = &client {
+                    answer(client, false).await;
+                }
+            }
+            Decision::Ignore(reason) => info!("[ready-check] ignoring, {}", reason),
+        }
+    }
+}
+
+async fn answer(client: &LcuClient, accept: bool) {
+    let result = if accept {
+        client.accept_ready_check().await
+    } else {
+        client.decline_ready_check().await
+    };
+    match result {
+        Ok(()) if accept => info!("[ready-check] accepted"),
+        Ok(()) => info!("[ready-check] declined"),
+        Err(e) => warn!("[ready-check] error answering: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{events::LcuEvent, mock::MockLcu};
+    use futures::channel::mpsc;
+    use serde_json::json;
+
+    fn pending() -> ReadyCheck {
+        serde_json::from_value(json!({
+            "state": "InProgress",
+            "playerResponse": "None",
+            "timer": 1.0,
+            "declinerIds": [],
+        }))
+        .unwrap()
+    }
+
+    fn event(event_type: EventType, check: &ReadyCheck) -> EventInput {
+        EventInput::Event(LcuEvent {
+            uri: READY_CHECK_URI.to_string(),
+            event_type,
+            data: serde_json::to_value(check).unwrap(),
+        })
+    }
+
+    async fn wait_for_changes(mock: &MockLcu, count: usize) -> Vec<(String, String)> {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let changes = mock.changes();
+                if changes.len() >= count {
+                    return changes;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn decide_ready_check() {
+        let check = pending();
+        let mut config = AutoAcceptConfig::default();
+        assert!(matches!(config.decide(&check, 12), Decision::Ignore(_)));
+
+        config.enabled = true;
+        config.delay = Duration::from_secs(1);
+        config.decline_rules = vec![DeclineRule::Hours { from: 23, to: 7 }];
+        assert_eq!(
+            config.decide(&check, 12),
+            Decision::Accept {
+                after: Duration::from_secs(1)
+            }
+        );
+        assert_eq!(
+            config.decide(&check, 2),
+            Decision::Decline(DeclineRule::Hours { from: 23, to: 7 })
+        );
+        assert!(matches!(config.decide(&check, 7), Decision::Accept { .. }));
+
+        let accepted = ReadyCheck {
+            player_response: PlayerResponse::Accepted,
+            ..pending()
+        };
+        assert!(matches!(config.decide(&accepted, 12), Decision::Ignore(_)));
+    }
+
+    #[test]
+    fn hour_ranges() {
+        let day = DeclineRule::Hours { from: 9, to: 17 };
+        assert!(day.applies(9));
+        assert!(!day.applies(17));
+        assert!(!day.applies(3));
+
+        let night = DeclineRule::Hours { from: 22, to: 6 };
+        assert!(night.applies(23));
+        assert!(night.applies(0));
+        assert!(!night.applies(6));
+        assert!(!night.applies(12));
+    }
+
+    #[tokio::test]
+    async fn accept_once_enabled() {
+        let mock = MockLcu::start().await;
+        let accept = format!("{READY_CHECK_URI}/accept");
+        mock.respond("GET", READY_CHECK_URI, 200, json!(ReadyCheck::default()));
+        mock.respond("POST", &accept, 204, json!(null));
+
+        let (tx, inputs) = mpsc::unbounded();
+        let config = AutoAcceptConfig {
+            delay: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let auto_accept = AutoAccept::follow(inputs, config);
+        assert!(!auto_accept.is_enabled());
+        tx.unbounded_send(EventInput::Connection(ConnectionEvent::Connected(mock.client())))
+            .unwrap();
+
+        // disabled, the check is left alone
+        tx.unbounded_send(event(EventType::Create, &pending()))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(mock.changes().is_empty());
+        tx.unbounded_send(event(EventType::Delete, &ReadyCheck::default()))
+            .unwrap();
+
+        auto_accept.enable();
+        tx.unbounded_send(event(EventType::Create, &pending()))
+            .unwrap();
+        // the timer ticks do not accept again
+        tx.unbounded_send(event(EventType::Update, &pending()))
+            .unwrap();
+        assert_eq!(
+            wait_for_changes(&mock, 1).await,
+            vec![("POST".to_string(), accept)]
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(mock.changes().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn decline_during_quiet_hours() {
+        let mock = MockLcu::start().await;
+        let decline = format!("{READY_CHECK_URI}/decline");
+        mock.respond("GET", READY_CHECK_URI, 200, json!(pending()));
+        mock.respond("POST", &decline, 204, json!(null));
+
+        let (tx, inputs) = mpsc::unbounded();
+        let config = AutoAcceptConfig {
+            enabled: true,
+            decline_rules: vec![DeclineRule::Hours { from: 0, to: 24 }],
+            ..Default::default()
+        };
+        let _auto_accept = AutoAccept::follow(inputs, config);
+        // a check popped before connecting is picked up too
+        tx.unbounded_send(EventInput::Connection(ConnectionEvent::Connected(mock.client())))
+            .unwrap();
+        tx.unbounded_send(event(EventType::Update, &pending()))
+            .unwrap();
+
+        assert_eq!(
+            wait_for_changes(&mock, 1).await,
+            vec![("POST".to_string(), decline)]
+        );
+    }
+}
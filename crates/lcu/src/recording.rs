@@ -0,0 +1,334 @@
+//! Captures websocket sessions to JSONL files and plays them back, so the
+//! champ select flow can be reproduced without queueing for a game.
+
+use std::{
+    fmt,
+    future::Future,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use futures::{Stream, StreamExt};
+use kv_log_macro::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+    sync::oneshot,
+    task::JoinHandle,
+    time::{Instant, Sleep},
+};
+
+use crate::events::{matches_prefix, LcuEvent, LcuEventClient};
+
+/// One line of a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedEvent {
+    /// Since the recording started.
+    pub elapsed_ms: u64,
+    /// Unix time in milliseconds.
+    pub timestamp: u64,
+    pub event: LcuEvent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingError {
+    Io(String),
+    /// `line` is 1-based.
+    Parse {
+        line: usize,
+        message: String,
+    },
+    /// Replay speeds must be positive, `f64::INFINITY` included.
+    InvalidSpeed(f64),
+}
+
+impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordingError::Io(msg) => write!(f, "recording io error: {msg}"),
+            RecordingError::Parse { line, message } => {
+                write!(f, "invalid recording at line {line}: {message}")
+            }
+            RecordingError::InvalidSpeed(speed) => write!(f, "invalid replay speed: {speed}"),
+        }
+    }
+}
+
+impl std::error::Error for RecordingError {}
+
+impl From<std::io::Error> for RecordingError {
+    fn from(error: std::io::Error) -> RecordingError {
+        RecordingError::Io(error.to_string())
+    }
+}
+
+/// Writes events to a JSONL file in the background.
+///
+/// Every line is flushed as it is written, so a recording stays readable if
+/// the app crashes mid-session. Dropping the recorder stops it as well.
+pub struct EventRecorder {
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<Result<usize, RecordingError>>,
+}
+
+impl EventRecorder {
+    /// Records every event of `client` into `path`, replacing the file.
+    pub async fn start(
+        client: &LcuEventClient,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, RecordingError> {
+        Self::record(client.subscribe(""), path).await
+    }
+
+    pub async fn record<S>(events: S, path: impl AsRef<Path>) -> Result<Self, RecordingError>
+    where
+        S: Stream<Item = LcuEvent> + Unpin + Send + 'static,
+    {
+        let path = path.as_ref();
+        let file = File::create(path).await?;
+        info!("[recording] writing events to {}", path.display());
+
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(write_events(events, BufWriter::new(file), stopped));
+        Ok(Self { stop, task })
+    }
+
+    /// Stops recording, returns how many events were written.
+    pub async fn stop(self) -> Result<usize, RecordingError> {
+        let _ = self.stop.send(());
+        match self.task.await {
+            Ok(result) => result,
+            Err(e) => Err(RecordingError::Io(e.to_string())),
+        }
+    }
+}
+
+async fn write_events<S>(
+    mut events: S,
+    mut file: BufWriter<File>,
+    mut stopped: oneshot::Receiver<()>,
+) -> Result<usize, RecordingError>
+where
+    S: Stream<Item = LcuEvent> + Unpin,
+{
+    let started = Instant::now();
+    let mut count = 0;
+
+    loop {
+        let event = tokio::select! {
+            _ = &mut stopped => break,
+            event = events.next() => match event {
+                Some(event) => event,
+                None => break,
+            },
+        };
+
+        let line = RecordedEvent {
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            timestamp: unix_millis(),
+            event,
+        };
+        let mut json = serde_json::to_vec(&line).map_err(|e| RecordingError::Io(e.to_string()))?;
+        json.push(b'\n');
+        file.write_all(&json).await?;
+        file.flush().await?;
+        count += 1;
+    }
+
+    info!("[recording] wrote {} events", count);
+    Ok(count)
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// A recorded session, read back from a JSONL file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+    pub events: Vec<RecordedEvent>,
+}
+
+impl Recording {
+    pub async fn read(path: impl AsRef<Path>) -> Result<Self, RecordingError> {
+        let text = tokio::fs::read_to_string(path).await?;
+        Self::parse(&text)
+    }
+
+    /// Parses JSONL, blank lines are skipped.
+    pub fn parse(text: &str) -> Result<Self, RecordingError> {
+        let mut events = vec![];
+        for (i, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: RecordedEvent =
+                serde_json::from_str(line).map_err(|e| RecordingError::Parse {
+                    line: i + 1,
+                    message: e.to_string(),
+                })?;
+            events.push(event);
+        }
+
+        if events.windows(2).any(|w| w[1].elapsed_ms < w[0].elapsed_ms) {
+            warn!("[recording] events are out of order, sorting them");
+            events.sort_by_key(|e| e.elapsed_ms);
+        }
+        Ok(Self { events })
+    }
+
+    /// Plays back the events below `uri_prefix`, like
+    /// [`LcuEventClient::subscribe`] would deliver them. A `speed` of 2 plays
+    /// twice as fast, `f64::INFINITY` without any delay.
+    pub fn replay(&self, uri_prefix: &str, speed: f64) -> Result<Replay, RecordingError> {
+        if speed.is_nan() || speed <= 0.0 {
+            return Err(RecordingError::InvalidSpeed(speed));
+        }
+        let events = self
+            .events
+            .iter()
+            .filter(|e| matches_prefix(uri_prefix, &e.event.uri))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Ok(Replay {
+            events: events.into_iter(),
+            speed,
+            started: None,
+            sleep: Box::pin(tokio::time::sleep(Duration::ZERO)),
+            pending: None,
+        })
+    }
+}
+
+/// Stream of recorded events, each delayed to its recorded offset from the
+/// first poll.
+pub struct Replay {
+    events: std::vec::IntoIter<RecordedEvent>,
+    speed: f64,
+    started: Option<Instant>,
+    sleep: Pin<Box<Sleep>>,
+    pending: Option<LcuEvent>,
+}
+
+impl Stream for Replay {
+    type Item = LcuEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<LcuEvent>> {
+        let this = &mut *self;
+        let started = *this.started.get_or_insert_with(Instant::now);
+
+        if this.pending.is_none() {
+            let Some(next) = this.events.next() else {
+                return Poll::Ready(None);
+            };
+            let offset = Duration::from_millis(next.elapsed_ms).div_f64(this.speed);
+            this.sleep.as_mut().reset(started + offset);
+            this.pending = Some(next.event);
+        }
+
+        match this.sleep.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(this.pending.take()),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    use futures::channel::mpsc;
+    use serde_json::json;
+
+    fn recorded(elapsed_ms: u64, uri: &str, data: serde_json::Value) -> RecordedEvent {
+        RecordedEvent {
+            elapsed_ms,
+            timestamp: 1_700_000_000_000 + elapsed_ms,
+            event: LcuEvent {
+                uri: uri.to_string(),
+                event_type: EventType::Update,
+                data,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn record_to_file() {
+        let path = std::env::temp_dir().join(format!("lcu-recording-{}.jsonl", nanoid::nanoid!(8)));
+        let (tx, events) = mpsc::unbounded();
+        let recorder = EventRecorder::record(events, &path).await.unwrap();
+
+        let phase = recorded(0, "/lol-gameflow/v1/gameflow-phase", json!("ChampSelect"));
+        tx.unbounded_send(phase.event.clone()).unwrap();
+        tx.unbounded_send(recorded(0, "/lol-champ-select/v1/session", json!({})).event)
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(recorder.stop().await.unwrap(), 2);
+
+        let recording = Recording::read(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.events.len(), 2);
+        assert_eq!(recording.events[0].event, phase.event);
+        assert!(recording.events[0].timestamp > 0);
+        assert!(recording.events[0].elapsed_ms <= recording.events[1].elapsed_ms);
+    }
+
+    #[test]
+    fn parse_recording() {
+        let line = |e: &RecordedEvent| serde_json::to_string(e).unwrap();
+        let first = recorded(10, "/lol-gameflow/v1/gameflow-phase", json!("Lobby"));
+        let second = recorded(5, "/lol-gameflow/v1/gameflow-phase", json!("None"));
+        let text = format!("{}\n\n{}\n", line(&first), line(&second));
+
+        let recording = Recording::parse(&text).unwrap();
+        assert_eq!(recording.events, vec![second, first]);
+        assert_eq!(
+            Recording::parse("{}\nnot json").unwrap_err(),
+            RecordingError::Parse {
+                line: 1,
+                message: "missing field `elapsedMs` at line 1 column 2".to_string()
+            }
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn replay_at_speed() {
+        let recording = Recording {
+            events: vec![
+                recorded(0, "/lol-gameflow/v1/gameflow-phase", json!("ChampSelect")),
+                recorded(1000, "/lol-champ-select/v1/session", json!({})),
+                recorded(4000, "/lol-gameflow/v1/gameflow-phase", json!("InProgress")),
+            ],
+        };
+
+        let started = Instant::now();
+        let mut replay = recording.replay("/lol-gameflow", 2.0).unwrap();
+        assert_eq!(replay.next().await.unwrap().data, json!("ChampSelect"));
+        assert_eq!(started.elapsed(), Duration::ZERO);
+        assert_eq!(replay.next().await.unwrap().data, json!("InProgress"));
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+        assert!(replay.next().await.is_none());
+
+        let started = Instant::now();
+        let all = recording
+            .replay("", f64::INFINITY)
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(all.len(), 3);
+        assert_eq!(started.elapsed(), Duration::ZERO);
+
+        assert!(matches!(
+            recording.replay("", 0.0),
+            Err(RecordingError::InvalidSpeed(_))
+        ));
+        assert!(recording.replay("", f64::NAN).is_err());
+    }
+}
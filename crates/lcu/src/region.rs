@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Platform the client is connected to, parsed from `--region=`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Region {
+    NA,
+    EUW,
+    EUNE,
+    KR,
+    JP,
+    BR,
+    LAN,
+    LAS,
+    OCE,
+    RU,
+    TR,
+    ME,
+    PBE,
+    Tencent,
+    Garena,
+    Unknown(String),
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Region::Unknown(String::new())
+    }
+}
+
+impl Region {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Region::NA => "NA",
+            Region::EUW => "EUW",
+            Region::EUNE => "EUNE",
+            Region::KR => "KR",
+            Region::JP => "JP",
+            Region::BR => "BR",
+            Region::LAN => "LA1",
+            Region::LAS => "LA2",
+            Region::OCE => "OC1",
+            Region::RU => "RU",
+            Region::TR => "TR",
+            Region::ME => "ME1",
+            Region::PBE => "PBE",
+            Region::Tencent => "TENCENT",
+            Region::Garena => "GARENA",
+            Region::Unknown(region) => region,
+        }
+    }
+
+    /// Tencent ships the game one level above the client, so configs live
+    /// under `Game/Config` instead of `Config`.
+    pub fn is_tencent(&self) -> bool {
+        matches!(self, Region::Tencent)
+    }
+
+    pub fn is_garena(&self) -> bool {
+        matches!(self, Region::Garena)
+    }
+}
+
+impl From<&str> for Region {
+    fn from(region: &str) -> Self {
+        match region.to_ascii_uppercase().as_str() {
+            "NA" | "NA1" => Region::NA,
+            "EUW" | "EUW1" => Region::EUW,
+            "EUNE" | "EUN1" => Region::EUNE,
+            "KR" => Region::KR,
+            "JP" | "JP1" => Region::JP,
+            "BR" | "BR1" => Region::BR,
+            "LAN" | "LA1" => Region::LAN,
+            "LAS" | "LA2" => Region::LAS,
+            "OCE" | "OC1" => Region::OCE,
+            "RU" => Region::RU,
+            "TR" | "TR1" => Region::TR,
+            "ME" | "ME1" => Region::ME,
+            "PBE" | "PBE1" => Region::PBE,
+            "TENCENT" => Region::Tencent,
+            // servers that were operated by Garena before moving to Riot
+            "GARENA" | "PH" | "SG" | "TH" | "TW" | "VN" => Region::Garena,
+            _ => Region::Unknown(region.to_string()),
+        }
+    }
+}
+
+impl From<String> for Region {
+    fn from(region: String) -> Self {
+        Region::from(region.as_str())
+    }
+}
+
+impl From<Region> for String {
+    fn from(region: Region) -> Self {
+        region.as_str().to_string()
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_regions() {
+        assert_eq!(Region::from("NA"), Region::NA);
+        assert_eq!(Region::from("la2"), Region::LAS);
+        assert_eq!(Region::from("TENCENT"), Region::Tencent);
+        assert_eq!(Region::from("VN"), Region::Garena);
+        assert_eq!(Region::from("XX9"), Region::Unknown("XX9".to_string()));
+        assert_eq!(Region::from(""), Region::default());
+        assert_eq!(String::from(Region::OCE), "OC1");
+    }
+
+    #[test]
+    fn serialize_as_string() {
+        assert_eq!(
+            serde_json::to_string(&Region::Tencent).unwrap(),
+            "\"TENCENT\""
+        );
+        assert_eq!(
+            serde_json::from_str::<Region>("\"EUW\"").unwrap(),
+            Region::EUW
+        );
+    }
+}
@@ -0,0 +1,477 @@
+//! Checks rune pages from build sources against the styles of the running
+//! client. Sources lag behind patches, and the client silently rejects pages
+//! with perks that moved or were removed.
+
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    builds::Rune,
+    lcu_api::{LcuClient, Perk, RuneStyle, Slot},
+    lcu_error::LcuError,
+};
+
+/// Keystone, three primary rows, two secondary perks and three stat shards.
+pub const PAGE_SIZE: usize = 9;
+const SUB_PERKS: usize = 2;
+const KEYSTONE_SLOT: &str = "kKeyStone";
+const STAT_MOD_SLOT: &str = "kStatMod";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuneIssue {
+    UnknownStyle(i64),
+    SubStyleNotAllowed {
+        primary: i64,
+        sub: i64,
+    },
+    PerkCount(usize),
+    UnknownPerk(i64),
+    /// The perk at `index` of `selected_perk_ids` does not belong to the
+    /// slot that position stands for.
+    WrongSlot {
+        index: usize,
+        perk_id: i64,
+        expected: String,
+    },
+    /// Both secondary perks come from the same row.
+    SameSubRow(i64, i64),
+}
+
+impl fmt::Display for RuneIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuneIssue::UnknownStyle(id) => write!(f, "unknown style {id}"),
+            RuneIssue::SubStyleNotAllowed { primary, sub } => {
+                write!(f, "style {sub} cannot be the secondary of {primary}")
+            }
+            RuneIssue::PerkCount(count) => {
+                write!(f, "expected {PAGE_SIZE} perks, found {count}")
+            }
+            RuneIssue::UnknownPerk(id) => write!(f, "unknown perk {id}"),
+            RuneIssue::WrongSlot {
+                index,
+                perk_id,
+                expected,
+            } => write!(f, "perk {perk_id} at {index} is not in {expected}"),
+            RuneIssue::SameSubRow(a, b) => {
+                write!(f, "secondary perks {a} and {b} share a row")
+            }
+        }
+    }
+}
+
+/// Whether [`LcuClient::appy_rune_and_builds`] checks the page first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RuneCheck {
+    #[default]
+    Off,
+    /// Fails the rune step if the page is invalid.
+    Reject,
+    /// Applies [`RuneValidator::repair`]ed pages.
+    Repair,
+}
+
+/// Styles and perks of the running client, see [`RuneValidator::fetch`].
+#[derive(Debug, Clone, Default)]
+pub struct RuneValidator {
+    styles: Vec<RuneStyle>,
+    perk_ids: HashSet<i64>,
+}
+
+impl RuneValidator {
+    pub fn new(styles: Vec<RuneStyle>, perks: &[Perk]) -> Self {
+        Self {
+            styles,
+            perk_ids: perks.iter().map(|p| p.id).collect(),
+        }
+    }
+
+    pub async fn fetch(client: &LcuClient) -> Result<Self, LcuError> {
+        let (styles, perks) =
+            futures::try_join!(client.list_all_styles(), client.list_all_perks())?;
+        Ok(Self::new(styles, &perks))
+    }
+
+    fn style(&self, id: i64) -> Option<&RuneStyle> {
+        self.styles.iter().find(|s| s.id == id)
+    }
+
+    /// Every problem of `rune`, empty if the client will accept it.
+    pub fn validate(&self, rune: &Rune) -> Vec<RuneIssue> {
+        let mut issues = vec![];
+        let ids = &rune.selected_perk_ids;
+
+        let primary = self.style(rune.primary_style_id);
+        let sub = self.style(rune.sub_style_id);
+        if primary.is_none() {
+            issues.push(RuneIssue::UnknownStyle(rune.primary_style_id));
+        }
+        if sub.is_none() {
+            issues.push(RuneIssue::UnknownStyle(rune.sub_style_id));
+        }
+        if ids.len() != PAGE_SIZE {
+            issues.push(RuneIssue::PerkCount(ids.len()));
+        }
+        issues.extend(
+            ids.iter()
+                .filter(|id| !self.perk_ids.contains(id))
+                .map(|id| RuneIssue::UnknownPerk(*id)),
+        );
+        let (Some(primary), Some(sub)) = (primary, sub) else {
+            return issues;
+        };
+        if !primary.allowed_sub_styles.contains(&sub.id) {
+            issues.push(RuneIssue::SubStyleNotAllowed {
+                primary: primary.id,
+                sub: sub.id,
+            });
+        }
+
+        let layout = Layout::of(primary, sub);
+        for (index, (slot, perk_id)) in layout.primary.iter().zip(ids).enumerate() {
+            if !slot.perks.contains(perk_id) {
+                issues.push(wrong_slot(index, *perk_id, slot_name(primary, slot)));
+            }
+        }
+
+        let sub_ids = ids.iter().skip(layout.primary.len()).take(SUB_PERKS);
+        let mut rows = vec![];
+        for (i, perk_id) in sub_ids.enumerate() {
+            match layout.sub_row(*perk_id) {
+                Some(row) => rows.push((row, *perk_id)),
+                None => issues.push(wrong_slot(
+                    layout.primary.len() + i,
+                    *perk_id,
+                    format!("the rows of {}", sub.name),
+                )),
+            }
+        }
+        if let [(a, first), (b, second)] = rows[..] {
+            if a == b {
+                issues.push(RuneIssue::SameSubRow(first, second));
+            }
+        }
+
+        let shard_start = layout.primary.len() + SUB_PERKS;
+        for (i, (slot, perk_id)) in layout
+            .shards
+            .iter()
+            .zip(ids.iter().skip(shard_start))
+            .enumerate()
+        {
+            if !slot.perks.contains(perk_id) {
+                issues.push(wrong_slot(
+                    shard_start + i,
+                    *perk_id,
+                    slot_name(primary, slot),
+                ));
+            }
+        }
+        issues
+    }
+
+    /// A valid page as close as possible to `rune`: perks in the right slot
+    /// are kept wherever they were listed, the rest come from the style
+    /// defaults. Only an unknown primary style cannot be repaired.
+    pub fn repair(&self, rune: &Rune) -> Result<Rune, Vec<RuneIssue>> {
+        let Some(primary) = self.style(rune.primary_style_id) else {
+            return Err(vec![RuneIssue::UnknownStyle(rune.primary_style_id)]);
+        };
+        let ids = &rune.selected_perk_ids;
+        let sub = self.repair_sub_style(primary, rune);
+        let layout = Layout::of(primary, sub);
+        let mut perks = Vec::with_capacity(PAGE_SIZE);
+
+        for (index, slot) in layout.primary.iter().enumerate() {
+            let perk = ids
+                .get(index)
+                .filter(|id| slot.perks.contains(id))
+                .or_else(|| ids.iter().find(|id| slot.perks.contains(id)))
+                .or_else(|| {
+                    primary
+                        .default_perks
+                        .iter()
+                        .find(|id| slot.perks.contains(id))
+                });
+            perks.extend(perk.or(slot.perks.first()));
+        }
+
+        let listed = ids.iter().skip(layout.primary.len()).take(SUB_PERKS);
+        let candidates = listed
+            .chain(ids.iter())
+            .chain(sub.default_perks.iter())
+            .copied()
+            .chain(
+                layout
+                    .sub_rows
+                    .iter()
+                    .filter_map(|s| s.perks.first().copied()),
+            );
+        let mut rows = vec![];
+        for perk_id in candidates {
+            if rows.len() == SUB_PERKS {
+                break;
+            }
+            if let Some(row) = layout.sub_row(perk_id).filter(|r| !rows.contains(r)) {
+                rows.push(row);
+                perks.push(perk_id);
+            }
+        }
+
+        let shard_start = layout.primary.len() + SUB_PERKS;
+        for (i, slot) in layout.shards.iter().enumerate() {
+            let perk = ids
+                .get(shard_start + i)
+                .filter(|id| slot.perks.contains(id))
+                .or_else(|| {
+                    primary
+                        .default_perks
+                        .get(shard_start + i)
+                        .filter(|id| slot.perks.contains(id))
+                });
+            perks.extend(perk.or(slot.perks.first()));
+        }
+
+        let repaired = Rune {
+            sub_style_id: sub.id,
+            selected_perk_ids: perks,
+            ..rune.clone()
+        };
+        let issues = self.validate(&repaired);
+        if issues.is_empty() {
+            Ok(repaired)
+        } else {
+            Err(issues)
+        }
+    }
+
+    /// Keeps an allowed sub style, otherwise picks the allowed one holding
+    /// most of the listed perks, then the primary's default.
+    fn repair_sub_style<'a>(&'a self, primary: &'a RuneStyle, rune: &Rune) -> &'a RuneStyle {
+        if let Some(sub) = self
+            .style(rune.sub_style_id)
+            .filter(|s| primary.allowed_sub_styles.contains(&s.id))
+        {
+            return sub;
+        }
+
+        let matches = |style: &RuneStyle| {
+            let layout = Layout::of(primary, style);
+            rune.selected_perk_ids
+                .iter()
+                .filter(|id| layout.sub_row(**id).is_some())
+                .count()
+        };
+        let allowed = primary
+            .allowed_sub_styles
+            .iter()
+            .filter_map(|id| self.style(*id));
+        let default = self.style(primary.default_sub_style);
+        allowed
+            .rev()
+            .max_by_key(|s| (matches(s), s.id == primary.default_sub_style))
+            .or(default)
+            .unwrap_or(primary)
+    }
+}
+
+/// Which slot every position of `selectedPerkIds` stands for.
+struct Layout<'a> {
+    primary: Vec<&'a Slot>,
+    sub_rows: Vec<&'a Slot>,
+    shards: Vec<&'a Slot>,
+}
+
+impl<'a> Layout<'a> {
+    fn of(primary: &'a RuneStyle, sub: &'a RuneStyle) -> Self {
+        let is_shard = |s: &&Slot| s.type_field == STAT_MOD_SLOT;
+        Self {
+            primary: primary.slots.iter().filter(|s| !is_shard(s)).collect(),
+            sub_rows: sub
+                .slots
+                .iter()
+                .filter(|s| !is_shard(s) && s.type_field != KEYSTONE_SLOT)
+                .collect(),
+            shards: primary.slots.iter().filter(is_shard).collect(),
+        }
+    }
+
+    fn sub_row(&self, perk_id: i64) -> Option<usize> {
+        self.sub_rows
+            .iter()
+            .position(|s| s.perks.contains(&perk_id))
+    }
+}
+
+fn wrong_slot(index: usize, perk_id: i64, expected: String) -> RuneIssue {
+    RuneIssue::WrongSlot {
+        index,
+        perk_id,
+        expected,
+    }
+}
+
+fn slot_name(style: &RuneStyle, slot: &Slot) -> String {
+    let index = style
+        .slots
+        .iter()
+        .position(|s| std::ptr::eq(s, slot))
+        .unwrap_or_default();
+    match slot.slot_label.as_str() {
+        "" => format!("{} slot {index}", style.name),
+        label => format!("{} slot {index} ({label})", style.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHARDS: [[i64; 3]; 3] = [[5008, 5005, 5007], [5008, 5002, 5003], [5001, 5002, 5003]];
+
+    fn style(id: i64, name: &str, rows: [[i64; 2]; 4], sub: i64) -> RuneStyle {
+        let slot = |perks: &[i64], type_field: &str, label: &str| Slot {
+            perks: perks.to_vec(),
+            slot_label: label.to_string(),
+            type_field: type_field.to_string(),
+        };
+        let mut slots = vec![slot(&rows[0], KEYSTONE_SLOT, "")];
+        slots.extend(
+            rows[1..]
+                .iter()
+                .map(|r| slot(r, "kMixedRegularSplashable", "")),
+        );
+        slots.extend(
+            ["Offense", "Flex", "Defense"]
+                .iter()
+                .zip(SHARDS)
+                .map(|(label, perks)| slot(&perks, STAT_MOD_SLOT, label)),
+        );
+
+        let mut default_perks: Vec<i64> = rows.iter().map(|r| r[0]).collect();
+        default_perks.extend([0, 0]);
+        default_perks.extend(SHARDS.iter().map(|s| s[0]));
+        RuneStyle {
+            id,
+            name: name.to_string(),
+            allowed_sub_styles: vec![sub],
+            default_sub_style: sub,
+            default_perks,
+            slots,
+            ..Default::default()
+        }
+    }
+
+    fn validator() -> RuneValidator {
+        let styles = vec![
+            style(
+                8000,
+                "Precision",
+                [[8005, 8008], [9101, 9111], [9104, 9105], [8014, 8017]],
+                8100,
+            ),
+            style(
+                8100,
+                "Domination",
+                [[8112, 8124], [8126, 8139], [8136, 8120], [8135, 8134]],
+                8000,
+            ),
+        ];
+        let perks: Vec<Perk> = styles
+            .iter()
+            .flat_map(|s| s.slots.iter().flat_map(|slot| slot.perks.clone()))
+            .map(|id| Perk {
+                id,
+                ..Default::default()
+            })
+            .collect();
+        RuneValidator::new(styles, &perks)
+    }
+
+    fn rune(primary: i64, sub: i64, perks: &[i64]) -> Rune {
+        Rune {
+            primary_style_id: primary,
+            sub_style_id: sub,
+            selected_perk_ids: perks.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn valid_page() {
+        let page = rune(
+            8000,
+            8100,
+            &[8005, 9111, 9104, 8017, 8139, 8135, 5005, 5008, 5002],
+        );
+        assert_eq!(validator().validate(&page), vec![]);
+        assert_eq!(validator().repair(&page).unwrap(), page);
+    }
+
+    #[test]
+    fn report_stale_page() {
+        let page = rune(
+            8000,
+            8000,
+            &[8008, 8014, 9104, 9999, 8126, 8139, 5005, 5001, 5002],
+        );
+        assert_eq!(
+            validator().validate(&page),
+            vec![
+                RuneIssue::UnknownPerk(9999),
+                RuneIssue::SubStyleNotAllowed {
+                    primary: 8000,
+                    sub: 8000
+                },
+                wrong_slot(1, 8014, "Precision slot 1".to_string()),
+                wrong_slot(3, 9999, "Precision slot 3".to_string()),
+                wrong_slot(4, 8126, "the rows of Precision".to_string()),
+                wrong_slot(5, 8139, "the rows of Precision".to_string()),
+                wrong_slot(7, 5001, "Precision slot 5 (Flex)".to_string()),
+            ]
+        );
+        assert_eq!(
+            validator().validate(&rune(8300, 8100, &[])),
+            vec![RuneIssue::UnknownStyle(8300), RuneIssue::PerkCount(0)]
+        );
+
+        let same_row = rune(
+            8000,
+            8100,
+            &[8005, 9111, 9104, 8017, 8126, 8139, 5005, 5008, 5002],
+        );
+        assert_eq!(
+            validator().validate(&same_row),
+            vec![RuneIssue::SameSubRow(8126, 8139)]
+        );
+        assert_eq!(
+            RuneIssue::SameSubRow(8126, 8139).to_string(),
+            "secondary perks 8126 and 8139 share a row"
+        );
+    }
+
+    #[test]
+    fn repair_stale_page() {
+        let page = rune(
+            8000,
+            8000,
+            &[8008, 8014, 9104, 9999, 8126, 8139, 5005, 5001, 5002],
+        );
+        let repaired = validator().repair(&page).unwrap();
+        // 8014 moves to its row, the empty row falls back to the default
+        assert_eq!(repaired.sub_style_id, 8100);
+        assert_eq!(
+            repaired.selected_perk_ids,
+            vec![8008, 9101, 9104, 8014, 8126, 8136, 5005, 5008, 5002]
+        );
+
+        assert_eq!(
+            validator().repair(&rune(8300, 8100, &[])),
+            Err(vec![RuneIssue::UnknownStyle(8300)])
+        );
+        let empty = validator().repair(&rune(8100, 0, &[])).unwrap();
+        assert_eq!(
+            empty.selected_perk_ids,
+            vec![8112, 8126, 8136, 8135, 9101, 9104, 5008, 5008, 5001]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+/// Numeric id of a summoner spell, from either a numeric id or a name as
+/// used by the sources, e.g. `"4"`, `"SummonerFlash"` or `"Flash"`.
+pub fn spell_id(identifier: &str) -> Option<u64> {
+    let identifier = identifier.trim();
+    if let Ok(id) = identifier.parse() {
+        return Some(id);
+    }
+
+    let name = identifier.to_lowercase();
+    let name = name.strip_prefix("summoner").unwrap_or(&name);
+    let id = match name {
+        "boost" | "cleanse" => 1,
+        "exhaust" => 3,
+        "flash" => 4,
+        "haste" | "ghost" => 6,
+        "heal" => 7,
+        "smite" => 11,
+        "teleport" => 12,
+        "mana" | "clarity" => 13,
+        "dot" | "ignite" => 14,
+        "barrier" => 21,
+        "snowball" | "mark" => 32,
+        _ => return None,
+    };
+    Some(id)
+}
+
+pub const FLASH: u64 = 4;
+
+/// Which key Flash goes on, the other spell takes the remaining slot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlashPreference {
+    /// Keep the order of the source.
+    #[default]
+    AsIs,
+    /// `spell1Id`
+    D,
+    /// `spell2Id`
+    F,
+}
+
+/// Both spell ids of a build ordered by `flash`, `None` unless there are
+/// exactly two known spells.
+pub fn resolve_spells(spells: &[String], flash: FlashPreference) -> Option<(u64, u64)> {
+    let [a, b] = spells else {
+        return None;
+    };
+    let (a, b) = (spell_id(a)?, spell_id(b)?);
+
+    let swap = match flash {
+        FlashPreference::AsIs => false,
+        FlashPreference::D => b == FLASH,
+        FlashPreference::F => a == FLASH,
+    };
+    Some(if swap { (b, a) } else { (a, b) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spells(a: &str, b: &str) -> Vec<String> {
+        vec![a.to_string(), b.to_string()]
+    }
+
+    #[test]
+    fn map_spell_identifiers() {
+        assert_eq!(spell_id("4"), Some(4));
+        assert_eq!(spell_id("SummonerFlash"), Some(4));
+        assert_eq!(spell_id("SummonerDot"), Some(14));
+        assert_eq!(spell_id("ignite"), Some(14));
+        assert_eq!(spell_id("SummonerSnowball"), Some(32));
+        assert_eq!(spell_id("Unknown"), None);
+    }
+
+    #[test]
+    fn keep_flash_on_preferred_key() {
+        let build = spells("SummonerFlash", "SummonerDot");
+        assert_eq!(resolve_spells(&build, FlashPreference::AsIs), Some((4, 14)));
+        assert_eq!(resolve_spells(&build, FlashPreference::D), Some((4, 14)));
+        assert_eq!(resolve_spells(&build, FlashPreference::F), Some((14, 4)));
+
+        let build = spells("12", "4");
+        assert_eq!(resolve_spells(&build, FlashPreference::D), Some((4, 12)));
+        assert_eq!(resolve_spells(&build, FlashPreference::F), Some((12, 4)));
+
+        // no flash, nothing to move
+        let build = spells("11", "6");
+        assert_eq!(resolve_spells(&build, FlashPreference::F), Some((11, 6)));
+
+        assert_eq!(
+            resolve_spells(&spells("4", "Nope"), FlashPreference::D),
+            None
+        );
+        assert_eq!(resolve_spells(&["4".to_string()], FlashPreference::D), None);
+    }
+}
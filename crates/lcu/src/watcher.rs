@@ -0,0 +1,296 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use kv_log_macro::{info, warn};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver},
+    task::JoinHandle,
+};
+
+use crate::{
+    cmd::{self, select_client, ClientSelector, CommandLineOutput},
+    discovery_error::DiscoveryError,
+};
+
+pub type DiscoverFn = Arc<dyn Fn() -> Result<Vec<CommandLineOutput>, DiscoveryError> + Send + Sync>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    ClientStarted(CommandLineOutput),
+    ClientStopped(CommandLineOutput),
+    CredentialsChanged(CommandLineOutput),
+}
+
+/// Polls for a running client in the background and reports changes.
+///
+/// A change must be observed for at least `debounce` before it is emitted, so
+/// a client restart between two polls shows up as `CredentialsChanged`
+/// instead of a stop and a start.
+#[derive(Clone)]
+pub struct ClientWatcher {
+    poll_interval: Duration,
+    debounce: Duration,
+    selector: ClientSelector,
+    discover: DiscoverFn,
+    lol_dir: Option<String>,
+}
+
+impl Default for ClientWatcher {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_millis(2500),
+            debounce: Duration::ZERO,
+            selector: ClientSelector::First,
+            discover: Arc::new(cmd::list_clients),
+            lol_dir: None,
+        }
+    }
+}
+
+impl ClientWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    pub fn selector(mut self, selector: ClientSelector) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    /// Install directory searched for the lockfile when the process list has
+    /// no client, see [`cmd::discover_client`].
+    pub fn lol_dir(mut self, lol_dir: impl Into<String>) -> Self {
+        self.lol_dir = Some(lol_dir.into());
+        self
+    }
+
+    /// Replaces the process list scan, e.g. with a fake in tests. The lockfile
+    /// fallback still applies.
+    pub fn discover_with<F>(mut self, discover: F) -> Self
+    where
+        F: Fn() -> Result<Vec<CommandLineOutput>, DiscoveryError> + Send + Sync + 'static,
+    {
+        self.discover = Arc::new(discover);
+        self
+    }
+
+    /// Starts polling on the tokio runtime. The task stops once the receiver
+    /// is dropped.
+    pub fn spawn(self) -> (JoinHandle<()>, UnboundedReceiver<ClientEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let handle = tokio::spawn(async move {
+            let mut current: Option<CommandLineOutput> = None;
+            let mut pending: Option<(Option<CommandLineOutput>, Instant)> = None;
+            let mut interval = tokio::time::interval(self.poll_interval);
+
+            loop {
+                interval.tick().await;
+                if tx.is_closed() {
+                    break;
+                }
+
+                let Some(observed) = self.observe(current.as_ref()).await else {
+                    continue;
+                };
+                if same_client(&current, &observed) {
+                    pending = None;
+                    continue;
+                }
+
+                let since = match &pending {
+                    Some((state, since)) if same_client(state, &observed) => *since,
+                    _ => {
+                        let now = Instant::now();
+                        pending = Some((observed.clone(), now));
+                        now
+                    }
+                };
+                if since.elapsed() < self.debounce {
+                    continue;
+                }
+
+                pending = None;
+                let event = match (current.take(), observed.clone()) {
+                    (None, Some(client)) => ClientEvent::ClientStarted(client),
+                    (Some(client), None) => ClientEvent::ClientStopped(client),
+                    (Some(_), Some(client)) => ClientEvent::CredentialsChanged(client),
+                    (None, None) => unreachable!(),
+                };
+                current = observed;
+
+                info!("[watcher] {:?}", &event);
+                if tx.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+
+        (handle, rx)
+    }
+
+    /// `None` when the client state is unknown for this tick, e.g. while the
+    /// client is starting and its command line is not complete yet.
+    async fn observe(
+        &self,
+        current: Option<&CommandLineOutput>,
+    ) -> Option<Option<CommandLineOutput>> {
+        let discover = self.discover.clone();
+        let lol_dir = self.lol_dir.clone();
+        let clients = match tokio::task::spawn_blocking(move || {
+            cmd::discover_clients_with(|| discover(), lol_dir.as_deref())
+        })
+        .await
+        {
+            Ok(Ok(clients)) => clients,
+            Ok(Err(DiscoveryError::ProcessNotFound)) => vec![],
+            Ok(Err(e)) => {
+                warn!("[watcher] {}", e);
+                return None;
+            }
+            Err(e) => {
+                warn!("[watcher] discovery task failed: {:?}", e);
+                return None;
+            }
+        };
+
+        // stick to the client we picked before, as long as it is still running
+        let tracked = current.and_then(|c| select_client(&clients, &ClientSelector::Pid(c.pid)));
+        Some(tracked.or_else(|| select_client(&clients, &self.selector)))
+    }
+}
+
+fn same_client(a: &Option<CommandLineOutput>, b: &Option<CommandLineOutput>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => a.pid == b.pid && a.port == b.port && a.token == b.token,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn client(pid: u32, token: &str) -> CommandLineOutput {
+        CommandLineOutput {
+            pid,
+            token: token.to_string(),
+            port: "51234".to_string(),
+            auth_url: cmd::make_auth_url(&token.to_string(), &"51234".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn fake_watcher(clients: &Arc<Mutex<Vec<CommandLineOutput>>>) -> ClientWatcher {
+        let clients = clients.clone();
+        ClientWatcher::new()
+            .poll_interval(Duration::from_millis(10))
+            .discover_with(move || Ok(clients.lock().unwrap().clone()))
+    }
+
+    async fn next_event(rx: &mut UnboundedReceiver<ClientEvent>) -> ClientEvent {
+        tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn emit_client_events() {
+        let clients = Arc::new(Mutex::new(vec![]));
+        let (_handle, mut rx) = fake_watcher(&clients).spawn();
+
+        *clients.lock().unwrap() = vec![client(1, "first")];
+        assert_eq!(
+            next_event(&mut rx).await,
+            ClientEvent::ClientStarted(client(1, "first"))
+        );
+
+        *clients.lock().unwrap() = vec![client(1, "second")];
+        assert_eq!(
+            next_event(&mut rx).await,
+            ClientEvent::CredentialsChanged(client(1, "second"))
+        );
+
+        clients.lock().unwrap().clear();
+        assert_eq!(
+            next_event(&mut rx).await,
+            ClientEvent::ClientStopped(client(1, "second"))
+        );
+    }
+
+    #[tokio::test]
+    async fn track_selected_client() {
+        let clients = Arc::new(Mutex::new(vec![client(1, "first")]));
+        let (_handle, mut rx) = fake_watcher(&clients).spawn();
+        assert_eq!(
+            next_event(&mut rx).await,
+            ClientEvent::ClientStarted(client(1, "first"))
+        );
+
+        // a second client shows up in front of the list, we keep the first one
+        clients.lock().unwrap().insert(0, client(2, "other"));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        clients.lock().unwrap().remove(1);
+        assert_eq!(
+            next_event(&mut rx).await,
+            ClientEvent::CredentialsChanged(client(2, "other"))
+        );
+    }
+
+    #[tokio::test]
+    async fn debounce_restart() {
+        let clients = Arc::new(Mutex::new(vec![client(1, "first")]));
+        let (_handle, mut rx) = fake_watcher(&clients)
+            .debounce(Duration::from_millis(100))
+            .spawn();
+        assert_eq!(
+            next_event(&mut rx).await,
+            ClientEvent::ClientStarted(client(1, "first"))
+        );
+
+        // a short gap while restarting is not reported as a stop
+        clients.lock().unwrap().clear();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        *clients.lock().unwrap() = vec![client(2, "second")];
+        assert_eq!(
+            next_event(&mut rx).await,
+            ClientEvent::CredentialsChanged(client(2, "second"))
+        );
+    }
+
+    #[tokio::test]
+    async fn fall_back_to_lockfile() {
+        let root = std::env::temp_dir().join(format!("champr_watcher_{}", nanoid::nanoid!(6)));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("lockfile"), "LeagueClient:7:2999:lock-token:https").unwrap();
+
+        let (_handle, mut rx) = ClientWatcher::new()
+            .poll_interval(Duration::from_millis(10))
+            .lol_dir(root.to_str().unwrap())
+            .discover_with(|| Err(DiscoveryError::PermissionDenied("/proc".to_string())))
+            .spawn();
+        match next_event(&mut rx).await {
+            ClientEvent::ClientStarted(client) => {
+                assert_eq!(client.pid, 7);
+                assert_eq!(client.port, "2999");
+                assert_eq!(client.token, "lock-token");
+            }
+            event => panic!("unexpected event: {:?}", event),
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -17,6 +17,8 @@ use tar::Archive;
 
 use crate::{
     builds::{self, BuildData, ItemBuild},
+    game_mode::GameMode,
+    install_dir::InstallDir,
     source::SourceItem,
 };
 
@@ -137,6 +139,30 @@ pub async fn list_builds_by_id(
     list_builds(&url).await
 }
 
+/// Builds of `champion_id` from every source that has builds for `mode`.
+pub async fn list_builds_for_mode(
+    sources: &[SourceItem],
+    mode: &GameMode,
+    champion_id: i64,
+) -> Vec<(String, Vec<builds::BuildSection>)> {
+    let tasks = mode.filter_sources(sources).into_iter().map(|s| async move {
+        let builds = list_builds_by_id(&s.value, champion_id).await;
+        (s.value.clone(), builds)
+    });
+
+    join_all(tasks)
+        .await
+        .into_iter()
+        .filter_map(|(source, builds)| match builds {
+            Ok(builds) => Some((source, builds)),
+            Err(_) => {
+                warn!("[web] no builds of {} for {}", champion_id, &source);
+                None
+            }
+        })
+        .collect()
+}
+
 pub async fn fetch_champion_runes(
     source: String,
     champion: String,
@@ -310,8 +336,7 @@ pub async fn read_from_local_folder(
 
 pub async fn download_tar_and_apply_for_source(
     source: &String,
-    lol_dir: Option<String>,
-    is_tencent: bool,
+    lol_dir: Option<InstallDir>,
 ) -> anyhow::Result<()> {
     let (_version, tar_url) = get_remote_package_data(source).await?;
 
@@ -331,13 +356,11 @@ pub async fn download_tar_and_apply_for_source(
 
     info!("found {} builds for {}", files.len(), source);
 
-    if lol_dir.is_some() {
-        let dir = lol_dir.unwrap();
-
+    if let Some(dir) = lol_dir {
         files.iter().for_each(|sections| {
             let sections = sections.clone();
             let alias = sections[0].alias.clone();
-            builds::apply_builds_from_data(sections, &dir.clone(), source, &alias, is_tencent);
+            builds::apply_builds_from_data(sections, &dir, source, &alias);
         });
     }
 
@@ -347,26 +370,43 @@ pub async fn download_tar_and_apply_for_source(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::region::Region;
+
+    fn temp_root(name: &str) -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(format!("champr_{name}_{}", nanoid::nanoid!(6)));
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
 
     #[tokio::test]
+    #[ignore = "downloads builds from the network"]
     async fn apply_builds_for_riot_server() -> anyhow::Result<()> {
         femme::with_level(femme::LevelFilter::Info);
 
+        let root = temp_root("riot_builds");
+        fs::create_dir_all(root.join("Config"))?;
+        fs::create_dir_all(root.join("Game"))?;
+        let install_dir = InstallDir::resolve(root.to_str().unwrap(), &Region::NA)?;
         let source = String::from("op.gg");
-        download_tar_and_apply_for_source(&source, Some(String::from(".local_builds")), false)
-            .await?;
+        download_tar_and_apply_for_source(&source, Some(install_dir)).await?;
 
+        fs::remove_dir_all(&root)?;
         Ok(())
     }
 
     #[tokio::test]
+    #[ignore = "downloads builds from the network"]
     async fn apply_builds_for_tencent_server() -> anyhow::Result<()> {
         femme::with_level(femme::LevelFilter::Info);
 
+        let root = temp_root("tencent_builds");
+        fs::create_dir_all(root.join("Game/Config"))?;
+        fs::create_dir_all(root.join("LeagueClient"))?;
+        let install_dir = InstallDir::resolve(root.to_str().unwrap(), &Region::Tencent)?;
         let source = String::from("op.gg");
-        download_tar_and_apply_for_source(&source, Some(String::from(".local_builds")), true)
-            .await?;
+        download_tar_and_apply_for_source(&source, Some(install_dir)).await?;
 
+        fs::remove_dir_all(&root)?;
         Ok(())
     }
 }